@@ -0,0 +1,129 @@
+//! AIRAC cycles, on the standard 28 day schedule.
+
+use chrono::{Datelike, Duration, NaiveDate};
+
+const CYCLE_DAYS: i64 = 28;
+
+// Start of cycle 2001
+fn reference() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2020, 1, 2).unwrap()
+}
+
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Cycle {
+    start: NaiveDate,
+}
+
+impl Cycle {
+    // Cycle in effect on the date
+    pub fn containing(date: NaiveDate) -> Cycle {
+        let n = (date - reference()).num_days().div_euclid(CYCLE_DAYS);
+        Cycle {
+            start: reference() + Duration::days(n * CYCLE_DAYS),
+        }
+    }
+
+    pub fn start(&self) -> NaiveDate {
+        self.start
+    }
+
+    pub fn next(&self) -> Cycle {
+        Cycle {
+            start: self.start + Duration::days(CYCLE_DAYS),
+        }
+    }
+
+    // Cycle identifier, two digit year and cycle number, e.g. "2209"
+    pub fn ident(&self) -> String {
+        let year = self.start.year();
+        let mut first = Cycle::containing(NaiveDate::from_ymd_opt(year, 1, 1).unwrap());
+        if first.start.year() < year {
+            first = first.next();
+        }
+        let n = (self.start - first.start).num_days() / CYCLE_DAYS + 1;
+        format!("{:02}{:02}", year % 100, n)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Validity {
+    Current,
+    // Superseded by a later cycle
+    Expired,
+    // Not yet in effect
+    Future,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Status {
+    // Cycle of the data, and the cycle in effect today
+    pub data: Cycle,
+    pub current: Cycle,
+    pub validity: Validity,
+}
+
+// Status of data for the release AIRAC date, e.g. "2022-09-08T00:00:00Z"
+pub fn status(airac_date: &str, today: NaiveDate) -> Result<Status, String> {
+    let date = airac_date
+        .get(..10)
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .ok_or_else(|| format!("invalid AIRAC date \"{}\"", airac_date))?;
+
+    let data = Cycle::containing(date);
+    let current = Cycle::containing(today);
+    let validity = match data.cmp(&current) {
+        std::cmp::Ordering::Less => Validity::Expired,
+        std::cmp::Ordering::Equal => Validity::Current,
+        std::cmp::Ordering::Greater => Validity::Future,
+    };
+
+    Ok(Status {
+        data,
+        current,
+        validity,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn cycles() {
+        let cycle = Cycle::containing(date(2022, 9, 20));
+        assert_eq!(cycle.start(), date(2022, 9, 8));
+        assert_eq!(cycle.ident(), "2209");
+        assert_eq!(cycle.next().start(), date(2022, 10, 6));
+
+        assert_eq!(Cycle::containing(date(2022, 9, 8)), cycle);
+        assert_eq!(Cycle::containing(date(2022, 9, 7)).ident(), "2208");
+
+        // First cycle of the year, and years with 14 cycles
+        assert_eq!(Cycle::containing(date(2022, 1, 26)).ident(), "2113");
+        assert_eq!(Cycle::containing(date(2022, 1, 27)).ident(), "2201");
+        assert_eq!(Cycle::containing(date(2020, 12, 31)).ident(), "2014");
+        assert_eq!(Cycle::containing(date(2019, 12, 5)).ident(), "1913");
+    }
+
+    #[test]
+    fn validity() {
+        let today = date(2022, 10, 1);
+        let s = status("2022-09-08T00:00:00Z", today).unwrap();
+        assert_eq!(s.validity, Validity::Current);
+        assert_eq!(s.current.next().start(), date(2022, 10, 6));
+
+        let s = status("2022-08-11T00:00:00Z", today).unwrap();
+        assert_eq!(s.validity, Validity::Expired);
+        assert_eq!(s.current.ident(), "2209");
+
+        let s = status("2022-10-06T00:00:00Z", today).unwrap();
+        assert_eq!(s.validity, Validity::Future);
+
+        assert!(status("", today).is_err());
+        assert!(status("2022-13-01", today).is_err());
+    }
+}
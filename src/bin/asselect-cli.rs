@@ -0,0 +1,417 @@
+//! Generate airspace files from a local YAIXM file.
+//!
+//! Usage: asselect-cli [OPTIONS] <YAIXM> <OUTPUT>
+//!
+//! With --igc the output is an airspace infringement report for the flight,
+//! and with --diff it's the airspace changes since an older release.
+
+use asselect::igc::{self, AltitudeSource, CheckOptions};
+use asselect::settings::{Format, Settings};
+use asselect::task::Task;
+use asselect::yaixm::convert::convert;
+use asselect::yaixm::diff::diff;
+use asselect::yaixm::geo::Distance;
+use asselect::yaixm::schema;
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use std::process::ExitCode;
+use std::{env, fs};
+
+const USAGE: &str = "\
+Usage: asselect-cli [OPTIONS] <YAIXM> <OUTPUT>
+
+Arguments:
+  <YAIXM>   YAIXM JSON data file, published or draft release
+  <OUTPUT>  Output file, \"-\" for standard output
+
+Options:
+  --settings <FILE>     Settings or profile JSON file, as saved by
+                        the web app
+  --format <FORMAT>     openair, ratonly, competition, geojson,
+                        tnp or kml
+  --max-level <FL>      Maximum flight level
+  --north <LAT>         Omit airspace north of latitude
+  --south <LAT>         Omit airspace south of latitude
+  --radio <yes|no>      Append frequency to name
+  --obstacle <yes|no>   Include obstacles
+  --home <NAME>         Exclude home airfield
+  --rat <NAME>          Include RA(T), may be repeated
+  --loa <NAME>          Include LOA, may be repeated
+  --wave <NAME>         Include wave box, may be repeated
+  --task <FILE>         Only include airspace near the task, from a
+                        task JSON or SeeYou .cup file
+  --task-buffer <DIST>  Distance either side of the task, e.g. \"5 nm\"
+  --igc <FILE>          Check IGC flight log for infringements
+  --qnh <HPA>           QNH for the infringement check
+  --altitude <SOURCE>   pressure or gnss altitude for the check
+  --diff <OLD>          Report changes from an older YAIXM file,
+                        instead of --igc
+  --diff-format <FMT>   text or json changes report
+  --produced <TIME>     RFC 3339 timestamp for the file header
+  -h, --help            Print help";
+
+// Parsed command line
+#[derive(Default)]
+struct Args {
+    yaixm: String,
+    output: String,
+    settings: Option<String>,
+    format: Option<Format>,
+    max_level: Option<u16>,
+    north: Option<f64>,
+    south: Option<f64>,
+    radio: Option<bool>,
+    obstacle: Option<bool>,
+    home: Option<String>,
+    rat: Option<HashSet<String>>,
+    loa: Option<HashSet<String>>,
+    wave: Option<HashSet<String>>,
+    task: Option<String>,
+    task_buffer: Option<Distance>,
+    igc: Option<String>,
+    qnh: Option<f64>,
+    altitude: Option<AltitudeSource>,
+    diff: Option<String>,
+    diff_json: bool,
+    produced: Option<DateTime<Utc>>,
+}
+
+fn parse_yes_no(value: &str) -> Result<bool, String> {
+    match value {
+        "yes" => Ok(true),
+        "no" => Ok(false),
+        _ => Err(format!("expected yes or no, got \"{}\"", value)),
+    }
+}
+
+fn parse_altitude(value: &str) -> Result<AltitudeSource, String> {
+    match value {
+        "pressure" => Ok(AltitudeSource::Pressure),
+        "gnss" => Ok(AltitudeSource::Gnss),
+        _ => Err(format!("expected pressure or gnss, got \"{}\"", value)),
+    }
+}
+
+fn parse_diff_format(value: &str) -> Result<bool, String> {
+    match value {
+        "text" => Ok(false),
+        "json" => Ok(true),
+        _ => Err(format!("expected text or json, got \"{}\"", value)),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse::<T>()
+        .map_err(|_| format!("invalid number \"{}\"", value))
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut parsed = Args::default();
+    let mut positional = Vec::new();
+
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            return Ok(None);
+        }
+
+        if !arg.starts_with("--") {
+            positional.push(arg);
+            continue;
+        }
+
+        let value = args
+            .next()
+            .ok_or_else(|| format!("missing value for {}", arg))?;
+
+        match arg.as_str() {
+            "--settings" => parsed.settings = Some(value),
+            "--format" => parsed.format = Some(value.parse()?),
+            "--max-level" => parsed.max_level = Some(parse_number(&value)?),
+            "--north" => parsed.north = Some(parse_number(&value)?),
+            "--south" => parsed.south = Some(parse_number(&value)?),
+            "--radio" => parsed.radio = Some(parse_yes_no(&value)?),
+            "--obstacle" => parsed.obstacle = Some(parse_yes_no(&value)?),
+            "--home" => parsed.home = Some(value),
+            "--rat" => {
+                parsed.rat.get_or_insert_with(HashSet::new).insert(value);
+            }
+            "--loa" => {
+                parsed.loa.get_or_insert_with(HashSet::new).insert(value);
+            }
+            "--wave" => {
+                parsed.wave.get_or_insert_with(HashSet::new).insert(value);
+            }
+            "--task" => parsed.task = Some(value),
+            "--task-buffer" => parsed.task_buffer = Some(value.parse()?),
+            "--igc" => parsed.igc = Some(value),
+            "--qnh" => parsed.qnh = Some(parse_number(&value)?),
+            "--altitude" => parsed.altitude = Some(parse_altitude(&value)?),
+            "--diff" => parsed.diff = Some(value),
+            "--diff-format" => parsed.diff_json = parse_diff_format(&value)?,
+            "--produced" => {
+                let produced = DateTime::parse_from_rfc3339(&value)
+                    .map_err(|e| format!("invalid timestamp \"{}\": {}", value, e))?;
+                parsed.produced = Some(produced.with_timezone(&Utc));
+            }
+            _ => return Err(format!("unknown option {}", arg)),
+        }
+    }
+
+    if parsed.diff.is_some() && parsed.igc.is_some() {
+        return Err("--diff and --igc can't be used together".to_string());
+    }
+
+    match <[String; 2]>::try_from(positional) {
+        Ok([yaixm, output]) => {
+            parsed.yaixm = yaixm;
+            parsed.output = output;
+            Ok(Some(parsed))
+        }
+        Err(_) => Err("expected <YAIXM> and <OUTPUT> arguments".to_string()),
+    }
+}
+
+// Settings file with command line overrides
+fn settings(args: &Args) -> Result<Settings, String> {
+    let mut settings = match &args.settings {
+        Some(path) => {
+            let data = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+            Settings::from_json(&data).map_err(|e| format!("{}: {}", path, e))?
+        }
+        None => Settings::default(),
+    };
+
+    if let Some(format) = &args.format {
+        settings.options.format = format.clone();
+    }
+    if let Some(max_level) = args.max_level {
+        settings.options.max_level = max_level;
+    }
+    if let Some(north) = args.north {
+        settings.options.north = north;
+    }
+    if let Some(south) = args.south {
+        settings.options.south = south;
+    }
+    if let Some(radio) = args.radio {
+        settings.options.radio = radio;
+    }
+    if let Some(obstacle) = args.obstacle {
+        settings.airspace.obstacle = obstacle;
+    }
+    if let Some(home) = &args.home {
+        settings.airspace.home = Some(home.clone());
+    }
+    if let Some(rat) = &args.rat {
+        settings.rat = rat.clone();
+    }
+    if let Some(loa) = &args.loa {
+        settings.loa = loa.clone();
+    }
+    if let Some(wave) = &args.wave {
+        settings.wave = wave.clone();
+    }
+    if let Some(path) = &args.task {
+        let data = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let task = if path.to_lowercase().ends_with(".cup") {
+            Task::from_cup(&data)
+        } else {
+            serde_json::from_str(&data).map_err(|e| e.to_string())
+        };
+        settings.task = Some(task.map_err(|e| format!("{}: {}", path, e))?);
+    }
+    if let Some(buffer) = args.task_buffer {
+        settings
+            .task
+            .as_mut()
+            .ok_or("--task-buffer needs a task")?
+            .buffer = buffer;
+    }
+
+    settings.validate()?;
+    Ok(settings)
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let settings = settings(&args)?;
+
+    let data = fs::read_to_string(&args.yaixm).map_err(|e| format!("{}: {}", args.yaixm, e))?;
+    let yaixm = schema::from_str(&data).map_err(|e| format!("{}: {}", args.yaixm, e))?;
+
+    let output = match (&args.diff, &args.igc) {
+        (Some(path), _) => {
+            let data = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+            let old = schema::from_str(&data).map_err(|e| format!("{}: {}", path, e))?;
+            let changes = diff(&old, &yaixm);
+            if args.diff_json {
+                changes.to_json() + "\n"
+            } else {
+                changes.to_text()
+            }
+        }
+        (None, Some(path)) => {
+            let data = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+            let flight = igc::parse(&data).map_err(|e| format!("{}: {}", path, e))?;
+            let mut options = CheckOptions::default();
+            if let Some(qnh) = args.qnh {
+                options.qnh = qnh;
+            }
+            if let Some(altitude) = args.altitude {
+                options.altitude = altitude;
+            }
+            let infringements = igc::check(&yaixm, &settings, &flight, &options);
+            igc::report(&flight, &infringements, &options)
+        }
+        (None, None) => convert(&yaixm, &settings, args.produced.unwrap_or_else(Utc::now)),
+    };
+
+    if args.output == "-" {
+        print!("{}", output);
+        Ok(())
+    } else {
+        fs::write(&args.output, output).map_err(|e| format!("{}: {}", args.output, e))
+    }
+}
+
+fn main() -> ExitCode {
+    match parse_args(env::args().skip(1)) {
+        Ok(Some(args)) => match run(args) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("asselect-cli: {}", e);
+                ExitCode::FAILURE
+            }
+        },
+        Ok(None) => {
+            println!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("asselect-cli: {}\n\n{}", e, USAGE);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Result<Option<Args>, String> {
+        parse_args(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn parse() {
+        let parsed = args(&[
+            "--rat",
+            "ROYAL FLIGHT",
+            "yaixm.json",
+            "--rat",
+            "AIR SHOW",
+            "--loa",
+            "CAMBRIDGE",
+            "--wave",
+            "WEST",
+            "--wave",
+            "EAST",
+            "--max-level",
+            "195",
+            "-",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(parsed.yaixm, "yaixm.json");
+        assert_eq!(parsed.output, "-");
+        assert_eq!(parsed.max_level, Some(195));
+        assert_eq!(parsed.rat.unwrap().len(), 2);
+        assert_eq!(parsed.loa.unwrap().len(), 1);
+        assert_eq!(parsed.wave.unwrap().len(), 2);
+
+        assert!(args(&["yaixm.json", "--help"]).unwrap().is_none());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = |a: &[&str]| args(a).err().unwrap();
+
+        assert_eq!(err(&["in", "out", "--rat"]), "missing value for --rat");
+        assert_eq!(
+            err(&["in", "out", "--colour", "red"]),
+            "unknown option --colour"
+        );
+        assert_eq!(err(&["in"]), "expected <YAIXM> and <OUTPUT> arguments");
+        assert_eq!(
+            err(&["in", "out", "extra"]),
+            "expected <YAIXM> and <OUTPUT> arguments"
+        );
+        assert_eq!(
+            err(&["in", "out", "--max-level", "high"]),
+            "invalid number \"high\""
+        );
+        assert_eq!(
+            err(&["in", "out", "--radio", "maybe"]),
+            "expected yes or no, got \"maybe\""
+        );
+        assert_eq!(
+            err(&["in", "out", "--diff", "old", "--igc", "flight"]),
+            "--diff and --igc can't be used together"
+        );
+    }
+
+    #[test]
+    fn overrides() {
+        let mut saved = Settings::default();
+        saved.options.max_level = 195;
+        saved.options.north = 54.9;
+        saved.rat.insert("OLD RAT".to_string());
+        saved.loa.insert("CAMBRIDGE".to_string());
+
+        let path = env::temp_dir().join(format!("asselect-cli-test-{}.json", std::process::id()));
+        fs::write(&path, serde_json::to_string(&saved).unwrap()).unwrap();
+        let path = path.to_str().unwrap();
+
+        let parsed = args(&[
+            "--settings",
+            path,
+            "--max-level",
+            "105",
+            "--rat",
+            "NEW RAT",
+            "--home",
+            "LASHAM",
+            "in",
+            "out",
+        ])
+        .unwrap()
+        .unwrap();
+        let result = settings(&parsed);
+
+        let parsed = args(&["--settings", path, "--south", "55.0", "in", "out"])
+            .unwrap()
+            .unwrap();
+        let invalid = settings(&parsed);
+        fs::remove_file(path).unwrap();
+
+        let result = result.unwrap();
+        assert_eq!(result.options.max_level, 105);
+        assert_eq!(result.options.north, 54.9);
+        assert_eq!(result.airspace.home.as_deref(), Some("LASHAM"));
+        assert_eq!(result.rat, HashSet::from(["NEW RAT".to_string()]));
+        assert_eq!(result.loa, saved.loa);
+
+        assert_eq!(
+            invalid.unwrap_err(),
+            "south latitude 55 isn't south of north latitude 54.9"
+        );
+    }
+
+    #[test]
+    fn task_buffer_without_task() {
+        let parsed = args(&["--task-buffer", "3 nm", "in", "out"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(settings(&parsed).unwrap_err(), "--task-buffer needs a task");
+    }
+}
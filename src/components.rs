@@ -1,13 +1,76 @@
+use crate::state::Control;
+use asselect::settings::{Airspace, Options};
+use yew::{html, Callback, Event, Html};
+
 pub mod airspace_tab;
 pub mod extra_panel;
 pub mod extra_tab;
+pub mod file_picker;
+pub mod import_preview;
 pub mod notam_tab;
 pub mod options_tab;
+pub mod profile_bar;
 pub mod tabs;
+pub mod whats_new_tab;
 
 pub use airspace_tab::AirspaceTab;
 pub use extra_panel::ExtraPanel;
 pub use extra_tab::ExtraTab;
+pub use file_picker::FilePicker;
+pub use import_preview::ImportPreview;
 pub use notam_tab::NotamTab;
 pub use options_tab::OptionsTab;
+pub use profile_bar::ProfileBar;
 pub use tabs::Tabs;
+pub use whats_new_tab::WhatsNewTab;
+
+// Form select controls, two to a row, with the current settings selected.
+// Extra options can be appended to a control
+pub fn control_rows(
+    controls: &[Control],
+    settings: &Airspace,
+    options: &Options,
+    onchange: &Callback<Event>,
+    extra: impl Fn(&Control) -> Html,
+) -> Html {
+    controls
+        .chunks(2)
+        .map(|row| {
+            html! {
+                <div class="columns">
+                { for row.iter().map(|control| html! {
+                    <div class="column is-one-third">
+                      <div class="field">
+                        <label class="label is-small">{control.label}</label>
+                        <div class="control">
+                          <div class="select is-fullwidth">
+                            <select name={control.name} onchange={onchange.clone()}>
+                              { control_options(control, settings, options) }
+                              { extra(control) }
+                            </select>
+                          </div>
+                        </div>
+                      </div>
+                    </div>
+                }) }
+                </div>
+            }
+        })
+        .collect()
+}
+
+// Select control options, with the current setting selected
+fn control_options(control: &Control, settings: &Airspace, options: &Options) -> Html {
+    control
+        .options
+        .iter()
+        .map(|(value, label)| {
+            let selected = control
+                .setting(value)
+                .is_ok_and(|setting| setting.selected(settings, options));
+            html! {
+                <option value={*value} {selected}>{*label}</option>
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,47 @@
+use crate::LocalFile;
+use gloo_file::callbacks::{read_as_text, FileReader};
+use gloo_file::File;
+use web_sys::HtmlInputElement;
+use yew::{function_component, html, use_mut_ref, Callback, Event, Properties, TargetCast};
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub label: String,
+    pub callback: Callback<LocalFile>,
+}
+
+#[function_component(FilePicker)]
+pub fn file_picker(props: &Props) -> Html {
+    // Reader must be kept until the file has been read
+    let reader = use_mut_ref(|| None::<FileReader>);
+
+    let onchange = {
+        let callback = props.callback.clone();
+        move |e: Event| {
+            let input = e.target_unchecked_into::<HtmlInputElement>();
+            if let Some(file) = input.files().and_then(|files| files.get(0)) {
+                let file = File::from(file);
+                let name = file.name();
+                let callback = callback.clone();
+                *reader.borrow_mut() = Some(read_as_text(&file, move |result| {
+                    callback.emit(LocalFile {
+                        name,
+                        data: result.map_err(|e| e.to_string()),
+                    })
+                }));
+            }
+            input.set_value("");
+        }
+    };
+
+    html! {
+        <div class="file">
+          <label class="file-label">
+            <input class="file-input" type="file" accept=".json,application/json" {onchange}/>
+            <span class="file-cta">
+              <span class="file-label">{&props.label}</span>
+            </span>
+          </label>
+        </div>
+    }
+}
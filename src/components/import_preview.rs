@@ -0,0 +1,52 @@
+use yew::{function_component, html, Callback, MouseEvent, Properties};
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub name: String,
+    pub changes: Vec<String>,
+    pub unknown: Vec<String>,
+    pub onapply: Callback<MouseEvent>,
+    pub oncancel: Callback<MouseEvent>,
+}
+
+#[function_component(ImportPreview)]
+pub fn import_preview(props: &Props) -> Html {
+    let changes = if props.changes.is_empty() {
+        html! {<p>{"The imported settings are the same as the current settings."}</p>}
+    } else {
+        html! {
+            <ul class="ml-4">
+              { for props.changes.iter().map(|c| html! {<li>{c}</li>}) }
+            </ul>
+        }
+    };
+
+    let unknown = if props.unknown.is_empty() {
+        html! {}
+    } else {
+        html! {
+            <div class="notification is-warning mt-2">
+              <p>{"These names aren't in the loaded airspace data and will be ignored:"}</p>
+              <ul class="ml-4">
+                { for props.unknown.iter().map(|n| html! {<li>{n}</li>}) }
+              </ul>
+            </div>
+        }
+    };
+
+    html! {
+        <div class="notification is-info is-light mx-4">
+          <p><strong>{format!("Import settings from {}", props.name)}</strong></p>
+          {changes}
+          {unknown}
+          <div class="buttons mt-2">
+            <button class="button is-small is-primary" onclick={props.onapply.clone()}>
+              {"Apply"}
+            </button>
+            <button class="button is-small" onclick={props.oncancel.clone()}>
+              {"Cancel"}
+            </button>
+          </div>
+        </div>
+    }
+}
@@ -0,0 +1,67 @@
+use crate::profiles::ProfileAction;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::{
+    function_component, html, Callback, Event, Html, MouseEvent, NodeRef, Properties, TargetCast,
+};
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub names: Vec<String>,
+    pub current: usize,
+    pub callback: Callback<ProfileAction>,
+}
+
+#[function_component(ProfileBar)]
+pub fn profile_bar(props: &Props) -> Html {
+    let name_ref = NodeRef::default();
+
+    let onswitch = props.callback.reform(|e: Event| {
+        let index = e
+            .target_unchecked_into::<HtmlSelectElement>()
+            .selected_index();
+        ProfileAction::Switch(index as usize)
+    });
+
+    // Button callback for actions taking the entered name
+    let named = |action: fn(String) -> ProfileAction| {
+        let name_ref = name_ref.clone();
+        props.callback.reform(move |_: MouseEvent| {
+            let name = name_ref.cast::<HtmlInputElement>().unwrap().value();
+            action(name)
+        })
+    };
+
+    let ondelete = props.callback.reform(|_: MouseEvent| ProfileAction::Delete);
+
+    let options = props
+        .names
+        .iter()
+        .enumerate()
+        .map(|(n, name)| {
+            html! {
+                <option selected={n == props.current}>{name}</option>
+            }
+        })
+        .collect::<Html>();
+
+    html! {
+        <div class="field is-grouped is-grouped-multiline mx-4">
+          <div class="control">
+            <div class="select is-small">
+              <select name="profile" onchange={onswitch}>
+                {options}
+              </select>
+            </div>
+          </div>
+          <div class="control">
+            <input class="input is-small" type="text" placeholder="Profile name" ref={name_ref.clone()}/>
+          </div>
+          <div class="control buttons has-addons">
+            <button class="button is-small" onclick={named(ProfileAction::Create)}>{"New"}</button>
+            <button class="button is-small" onclick={named(ProfileAction::Rename)}>{"Rename"}</button>
+            <button class="button is-small" onclick={named(ProfileAction::Duplicate)}>{"Duplicate"}</button>
+            <button class="button is-small is-danger is-light" onclick={ondelete}>{"Delete"}</button>
+          </div>
+        </div>
+    }
+}
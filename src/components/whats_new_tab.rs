@@ -0,0 +1,82 @@
+use asselect::yaixm::diff::{Diff, VolumeRecord};
+use yew::{function_component, html, Html, Properties};
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub changes: Option<Diff>,
+}
+
+fn volume_list(title: &str, records: &[VolumeRecord]) -> Html {
+    if records.is_empty() {
+        return html! {};
+    }
+
+    html! {
+        <div class="block">
+          <div class="has-text-weight-semibold">
+            {format!("{} ({})", title, records.len())}
+          </div>
+          <ul class="ml-4">
+            { for records.iter().map(|r| html! {<li>{r.to_string()}</li>}) }
+          </ul>
+        </div>
+    }
+}
+
+#[function_component(WhatsNewTab)]
+pub fn whats_new_tab(props: &Props) -> Html {
+    let Some(changes) = &props.changes else {
+        return html! {
+            <div class="block">
+              {"No changes recorded yet. Changes are shown once a new airspace release has been downloaded."}
+            </div>
+        };
+    };
+
+    let modified = if changes.modified.is_empty() {
+        html! {}
+    } else {
+        html! {
+            <div class="block">
+              <div class="has-text-weight-semibold">
+                {format!("Modified ({})", changes.modified.len())}
+              </div>
+              <ul class="ml-4">
+              {
+                for changes.modified.iter().map(|m| html! {
+                  <li>
+                    {m.volume.to_string()}
+                    <ul class="ml-4">
+                    {
+                      for m.changes.iter().map(|c| html! {
+                        <li class="is-size-7">
+                          {format!("{}: {} \u{2192} {}", c.field, c.old, c.new)}
+                        </li>
+                      })
+                    }
+                    </ul>
+                  </li>
+                })
+              }
+              </ul>
+            </div>
+        }
+    };
+
+    html! {
+        <div>
+          <div class="subtitle">
+            {"What's New"}
+          </div>
+          <div class="block">
+            {format!("Changes from {} to {}", changes.old_release, changes.new_release)}
+          </div>
+          if changes.is_empty() {
+            <div class="block">{"No airspace changes"}</div>
+          }
+          {volume_list("Added", &changes.added)}
+          {volume_list("Removed", &changes.removed)}
+          {modified}
+        </div>
+    }
+}
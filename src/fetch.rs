@@ -0,0 +1,88 @@
+use asselect::yaixm::diff::{diff, Diff};
+use asselect::yaixm::{schema, Yaixm};
+use gloo_net::http::Request;
+use gloo_net::Error;
+use gloo_storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+
+// Local storage key for the last good YAIXM data
+const CACHE_KEY: &str = "yaixm_cache";
+
+// Local storage key for changes between the last two cached releases
+const CHANGES_KEY: &str = "yaixm_changes";
+
+// Raw YAIXM data, identified by its release
+#[derive(Deserialize, Serialize)]
+struct Cache {
+    airac_date: String,
+    commit: String,
+    data: String,
+}
+
+// Source of the loaded YAIXM data
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DataSource {
+    Server,
+    Cache,
+    // Local file name
+    Local(String),
+}
+
+// Get YAIXM data from server, and cache it for offline use
+pub async fn fetch_yaixm() -> Result<Yaixm, Error> {
+    let response = Request::get("yaixm.json").send().await?;
+    if !response.ok() {
+        return Err(Error::GlooError(format!(
+            "yaixm.json: {} {}",
+            response.status(),
+            response.status_text()
+        )));
+    }
+
+    let data = response.text().await?;
+    let yaixm = schema::from_str(&data).map_err(Error::GlooError)?;
+
+    store_cache(&yaixm, data);
+    Ok(yaixm)
+}
+
+// Save data, unless the same release is already cached
+fn store_cache(yaixm: &Yaixm, data: String) {
+    let release = &yaixm.release;
+    let cached: Option<Cache> = LocalStorage::get(CACHE_KEY).ok();
+    if let Some(cached) = cached {
+        if cached.airac_date == release.airac_date && cached.commit == release.commit {
+            return;
+        }
+
+        // Record what's changed since the previous release
+        match schema::from_str(&cached.data) {
+            Ok(old) => {
+                if let Err(e) = LocalStorage::set(CHANGES_KEY, diff(&old, yaixm)) {
+                    log::warn!("Can't save airspace changes: {}", e);
+                }
+            }
+            Err(e) => log::warn!("Can't compare with cached airspace data: {}", e),
+        }
+    }
+
+    let cache = Cache {
+        airac_date: release.airac_date.clone(),
+        commit: release.commit.clone(),
+        data,
+    };
+    if let Err(e) = LocalStorage::set(CACHE_KEY, cache) {
+        log::warn!("Can't cache airspace data: {}", e);
+    }
+}
+
+// Last good YAIXM data
+pub fn cached_yaixm() -> Option<Yaixm> {
+    let cache: Cache = LocalStorage::get(CACHE_KEY).ok()?;
+    schema::from_str(&cache.data).ok()
+}
+
+// Changes in the latest release, if known
+pub fn release_changes() -> Option<Diff> {
+    LocalStorage::get(CHANGES_KEY).ok()
+}
@@ -0,0 +1,379 @@
+//! IGC flight log airspace infringement checking.
+//!
+//! Each B-record fix is checked against the merged airspace selected by
+//! the settings, as written by `openair()`. Flight levels are compared
+//! with standard pressure altitude and altitudes with altitude on the
+//! given QNH. There is no terrain model, so heights above ground are
+//! treated as altitudes with the ground at sea level.
+
+use crate::settings::Settings;
+use crate::yaixm::convert::{airfilter, merged_airspace, volume_name, volume_type};
+use crate::yaixm::geo::LatLon;
+use crate::yaixm::index::{SpatialIndex, VolumeRef};
+use crate::yaixm::level::Level;
+use crate::yaixm::query::{horizontal_margin, vertical_margin, Altitude, STANDARD_QNH};
+use crate::yaixm::Yaixm;
+use chrono::{NaiveDate, NaiveTime};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+const METRES_TO_FEET: f64 = 1.0 / 0.3048;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fix {
+    pub time: NaiveTime,
+    pub position: LatLon,
+    // 3D (GNSS altitude) fix
+    pub valid: bool,
+    // Altitudes in metres, pressure altitude is relative to 1013.25 hPa
+    pub pressure_alt: i32,
+    pub gnss_alt: i32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Flight {
+    pub date: Option<NaiveDate>,
+    pub fixes: Vec<Fix>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AltitudeSource {
+    Pressure,
+    Gnss,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CheckOptions {
+    pub qnh: f64,
+    pub altitude: AltitudeSource,
+}
+
+impl Default for CheckOptions {
+    fn default() -> Self {
+        CheckOptions {
+            qnh: STANDARD_QNH,
+            altitude: AltitudeSource::Pressure,
+        }
+    }
+}
+
+// Consecutive fixes inside a single volume
+#[derive(Clone, Debug, PartialEq)]
+pub struct Infringement {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    // First fix inside the volume
+    pub position: LatLon,
+    pub name: String,
+    pub class: &'static str,
+    pub lower: Level,
+    pub upper: Level,
+    // Maximum penetration, nautical miles inside the boundary and feet
+    // inside the vertical limits
+    pub horizontal: f64,
+    pub vertical: f64,
+}
+
+// IGC angle, degrees, minutes and thousandths of minutes, e.g. "5110200N"
+fn parse_angle(value: &str, deg_len: usize, pos: char, neg: char) -> Option<f64> {
+    let (digits, hemisphere) = value.split_at(value.len() - 1);
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let deg = digits[..deg_len].parse::<f64>().ok()?;
+    let min = digits[deg_len..].parse::<f64>().ok()? / 1000.0;
+    if min >= 60.0 {
+        return None;
+    }
+
+    match hemisphere.chars().next() {
+        Some(h) if h == pos => Some(deg + min / 60.0),
+        Some(h) if h == neg => Some(-(deg + min / 60.0)),
+        _ => None,
+    }
+}
+
+// B-record, e.g. "B1101355206343N00006198WA0058700558"
+fn parse_fix(line: &str) -> Option<Fix> {
+    if !line.is_ascii() || line.len() < 35 {
+        return None;
+    }
+
+    let time = NaiveTime::parse_from_str(&line[1..7], "%H%M%S").ok()?;
+    let lat = parse_angle(&line[7..15], 2, 'N', 'S')?;
+    let lon = parse_angle(&line[15..24], 3, 'E', 'W')?;
+    let valid = match &line[24..25] {
+        "A" => true,
+        "V" => false,
+        _ => return None,
+    };
+
+    Some(Fix {
+        time,
+        position: LatLon { lat, lon },
+        valid,
+        pressure_alt: line[25..30].parse().ok()?,
+        gnss_alt: line[30..35].parse().ok()?,
+    })
+}
+
+// Flight date, e.g. "HFDTE020922" or "HFDTEDATE:020922,01"
+fn parse_date(line: &str) -> Option<NaiveDate> {
+    let value = line.strip_prefix("HFDTE")?;
+    let value = value.strip_prefix("DATE:").unwrap_or(value);
+    NaiveDate::parse_from_str(value.get(..6)?, "%d%m%y").ok()
+}
+
+// Parse IGC file fixes
+pub fn parse(text: &str) -> Result<Flight, String> {
+    let mut flight = Flight {
+        date: None,
+        fixes: Vec::new(),
+    };
+
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        if line.starts_with('B') {
+            let fix = parse_fix(line).ok_or_else(|| format!("line {}: invalid fix", n + 1))?;
+            flight.fixes.push(fix);
+        } else if line.starts_with("HFDTE") {
+            flight.date = parse_date(line);
+        }
+    }
+
+    if flight.fixes.is_empty() {
+        Err("no fixes in file".to_string())
+    } else {
+        Ok(flight)
+    }
+}
+
+// Fix altitude from the selected source. 2D (V) fixes have no usable
+// GNSS altitude, so pressure altitude is used for them instead
+fn altitude(fix: &Fix, options: &CheckOptions) -> Altitude {
+    match options.altitude {
+        AltitudeSource::Gnss if fix.valid => {
+            Altitude::from_qnh(fix.gnss_alt as f64 * METRES_TO_FEET, options.qnh)
+        }
+        _ => Altitude::from_pressure(fix.pressure_alt as f64 * METRES_TO_FEET, options.qnh),
+    }
+}
+
+// Check flight against the selected airspace
+pub fn check(
+    yaixm: &Yaixm,
+    settings: &Settings,
+    flight: &Flight,
+    options: &CheckOptions,
+) -> Vec<Infringement> {
+    let airspace = merged_airspace(yaixm, settings);
+    let index =
+        SpatialIndex::with_filter(&airspace, |feature, vol| airfilter(feature, vol, settings));
+
+    let mut infringements: Vec<Infringement> = Vec::new();
+
+    // Index of infringement for each volume the previous fix was inside
+    let mut current: HashMap<VolumeRef, usize> = HashMap::new();
+
+    for fix in &flight.fixes {
+        let altitude = altitude(fix, options);
+        let mut inside = HashMap::new();
+
+        for vref in index.point(&fix.position) {
+            let feature = &airspace[vref.feature];
+            let vol = &feature.geometry[vref.volume];
+
+            let vertical = vertical_margin(vol, &altitude);
+            let horizontal = (vertical > 0.0)
+                .then(|| horizontal_margin(vol, &fix.position))
+                .flatten();
+            let Some(horizontal) = horizontal else {
+                continue;
+            };
+
+            match current.get(&vref) {
+                Some(&i) => {
+                    let inf = &mut infringements[i];
+                    inf.end = fix.time;
+                    inf.horizontal = inf.horizontal.max(horizontal);
+                    inf.vertical = inf.vertical.max(vertical);
+                    inside.insert(vref, i);
+                }
+                None => {
+                    inside.insert(vref, infringements.len());
+                    infringements.push(Infringement {
+                        start: fix.time,
+                        end: fix.time,
+                        position: fix.position,
+                        name: volume_name(feature, vol, vref.volume, settings),
+                        class: volume_type(feature, vol, settings),
+                        lower: vol.lower,
+                        upper: vol.upper,
+                        horizontal,
+                        vertical,
+                    });
+                }
+            }
+        }
+        current = inside;
+    }
+
+    infringements
+}
+
+// Text report
+pub fn report(flight: &Flight, infringements: &[Infringement], options: &CheckOptions) -> String {
+    let mut output = String::new();
+    if let Some(date) = flight.date {
+        writeln!(output, "Date: {}", date).unwrap();
+    }
+    let source = match options.altitude {
+        AltitudeSource::Pressure => "pressure",
+        AltitudeSource::Gnss => "GNSS",
+    };
+    writeln!(output, "Altitude: {}, QNH {} hPa", source, options.qnh).unwrap();
+    writeln!(output, "Fixes: {}", flight.fixes.len()).unwrap();
+    writeln!(output, "Infringements: {}", infringements.len()).unwrap();
+
+    for inf in infringements {
+        writeln!(
+            output,
+            "{}-{} {} {} [{}] {}-{}, {:.2} nm, {:.0} ft",
+            inf.start,
+            inf.end,
+            inf.position,
+            inf.name,
+            inf.class,
+            inf.lower,
+            inf.upper,
+            inf.horizontal,
+            inf.vertical
+        )
+        .unwrap();
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::yaixm::testdata;
+    use crate::yaixm::Volume;
+
+    // Brize Norton CTR is a 5 nm circle at 514500N 0013500W, SFC to 3500 ft
+    const IGC: &str = "\
+AXXX001
+HFDTEDATE:080922,01
+B1200005130000N00135000WA0030000350
+B1201005143000N00135000WA0030000350
+B1202005145000N00135000WA0030000350
+B1203005145000N00135000WA0120001230
+B1204005148000N00135000WA0030000350
+B1205005200000N00135000WA0030000350
+";
+
+    #[test]
+    fn parse_igc() {
+        let flight = parse(IGC).unwrap();
+        assert_eq!(flight.date, NaiveDate::from_ymd_opt(2022, 9, 8));
+        assert_eq!(flight.fixes.len(), 6);
+
+        let fix = flight.fixes[1];
+        assert_eq!(fix.time, NaiveTime::from_hms_opt(12, 1, 0).unwrap());
+        assert_eq!(fix.position.to_string(), "514300N 0013500W");
+        assert!(fix.valid);
+        assert_eq!((fix.pressure_alt, fix.gnss_alt), (300, 350));
+
+        let fix = parse_fix("B1101355206343S00006198EV-001200558").unwrap();
+        assert!(!fix.valid);
+        assert!(fix.position.lat < 0.0 && fix.position.lon > 0.0);
+        assert_eq!(fix.pressure_alt, -12);
+
+        assert!(parse("AXXX\n").is_err());
+        assert!(parse("B1260005130000N00135000WA0030000350\n").is_err());
+        assert!(parse("B1200005130000N00135000XA0030000350\n").is_err());
+    }
+
+    #[test]
+    fn infringements() {
+        let yaixm = testdata::yaixm();
+        let settings = Settings::default();
+        let flight = parse(IGC).unwrap();
+
+        let infs = check(&yaixm, &settings, &flight, &CheckOptions::default());
+        assert_eq!(infs.len(), 2);
+
+        let inf = &infs[0];
+        assert_eq!(inf.start, NaiveTime::from_hms_opt(12, 1, 0).unwrap());
+        assert_eq!(inf.end, NaiveTime::from_hms_opt(12, 2, 0).unwrap());
+        assert_eq!(inf.name, "BRIZE NORTON");
+        assert_eq!(inf.class, "D");
+        assert!((inf.horizontal - 5.0).abs() < 0.01);
+        assert!((inf.vertical - (3500.0 - 300.0 / 0.3048)).abs() < 0.01);
+
+        // Back in the zone after climbing out of the top
+        assert_eq!(infs[1].start, NaiveTime::from_hms_opt(12, 4, 0).unwrap());
+        assert!((infs[1].horizontal - 2.0).abs() < 0.01);
+
+        // 1200 m pressure altitude is below 3500 ft on a low QNH
+        let options = CheckOptions {
+            qnh: 980.0,
+            altitude: AltitudeSource::Pressure,
+        };
+        let infs = check(&yaixm, &settings, &flight, &options);
+        assert_eq!(infs.len(), 1);
+        assert_eq!(infs[0].end, NaiveTime::from_hms_opt(12, 4, 0).unwrap());
+
+        // GNSS altitude is higher
+        let options = CheckOptions {
+            qnh: STANDARD_QNH,
+            altitude: AltitudeSource::Gnss,
+        };
+        let infs = check(&yaixm, &settings, &flight, &options);
+        assert!((infs[0].vertical - (3500.0 - 350.0 / 0.3048)).abs() < 0.01);
+
+        // 2D fix in the zone at 350 m GNSS altitude, but 1200 m pressure
+        // altitude is above it
+        let flight = parse(
+            "\
+AXXX001
+B1200005130000N00135000WA0030000350
+B1201005143000N00135000WV0120000350
+B1202005200000N00135000WA0030000350
+",
+        )
+        .unwrap();
+        assert!(!flight.fixes[1].valid);
+        assert!(check(&yaixm, &settings, &flight, &options).is_empty());
+    }
+
+    #[test]
+    fn flight_levels() {
+        let vol: Volume = serde_json::from_str(
+            r#"{"lower": "FL45", "upper": "5000 ft",
+                "boundary": [{"circle": {"centre": "510000N 0010000W", "radius": "1 nm"}}]}"#,
+        )
+        .unwrap();
+
+        // 4600 ft on 1013 hPa is 4327 ft on QNH 1003.25
+        let options = CheckOptions {
+            qnh: 1003.25,
+            altitude: AltitudeSource::Pressure,
+        };
+        let fix = Fix {
+            time: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
+            position: LatLon {
+                lat: 51.0,
+                lon: -1.0,
+            },
+            valid: true,
+            pressure_alt: (4600.0 * 0.3048) as i32,
+            gnss_alt: 0,
+        };
+        let alt = altitude(&fix, &options);
+        assert!((alt.qnh - alt.pressure + 273.0).abs() < 1e-6);
+        let v = vertical_margin(&vol, &alt);
+        assert!(v > 0.0 && v < 100.0);
+    }
+}
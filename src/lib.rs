@@ -0,0 +1,11 @@
+//! Platform independent UK airspace conversion.
+//!
+//! The web interface is built on top of this library when the `web` feature
+//! is enabled, but nothing here depends on wasm or a browser.
+
+pub mod airac;
+pub mod igc;
+pub mod settings;
+pub mod share;
+pub mod task;
+pub mod yaixm;
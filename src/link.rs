@@ -0,0 +1,41 @@
+use asselect::settings::Settings;
+use asselect::share;
+use gloo_utils::window;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+
+// Settings from the page URL fragment, if there is one. The fragment is
+// then removed so a page reload uses the stored profile
+pub fn link_settings() -> Option<Result<Settings, String>> {
+    let location = window().location();
+    let hash = location.hash().ok().filter(|h| h.len() > 1)?;
+
+    if let (Ok(path), Ok(search)) = (location.pathname(), location.search()) {
+        let path = path + &search;
+        window()
+            .history()
+            .and_then(|h| h.replace_state_with_url(&JsValue::NULL, "", Some(&path)))
+            .ok();
+    }
+    Some(share::decode(&hash))
+}
+
+// Page URL for the settings
+pub fn share_url(settings: &Settings) -> String {
+    let location = window().location();
+    format!(
+        "{}{}{}#{}",
+        location.origin().unwrap_or_default(),
+        location.pathname().unwrap_or_default(),
+        location.search().unwrap_or_default(),
+        share::encode(settings)
+    )
+}
+
+pub async fn copy_to_clipboard(text: &str) -> Result<(), String> {
+    let promise = window().navigator().clipboard().write_text(text);
+    JsFuture::from(promise)
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("Can't copy link: {:?}", e))
+}
@@ -1,19 +1,31 @@
 #![allow(clippy::let_unit_value)]
 
 use gloo_file::{Blob, ObjectUrl};
-use gloo_storage::{LocalStorage, Storage};
 use gloo_utils::document;
 use wasm_bindgen::JsCast;
 use yew::{function_component, html, use_effect_with_deps, use_reducer, use_state, Callback};
 
-use components::{AirspaceTab, ExtraPanel, ExtraTab, NotamTab, OptionsTab, Tabs};
-use state::{Action, State};
-use yaixm::convert::openair;
-use yaixm::util::{fetch_yaixm, gliding_sites, loa_names, rat_names, wav_names};
+use asselect::airac::{self, Validity};
+use asselect::settings::Settings;
+use asselect::yaixm::convert::convert;
+use asselect::yaixm::util::{gliding_sites, loa_names, rat_names, wav_names};
+use asselect::yaixm::{schema, Yaixm};
+use chrono::Utc;
+use components::{
+    AirspaceTab, ExtraPanel, ExtraTab, FilePicker, ImportPreview, NotamTab, OptionsTab, ProfileBar,
+    Tabs, WhatsNewTab,
+};
+use fetch::{cached_yaixm, fetch_yaixm, release_changes, DataSource};
+use link::{copy_to_clipboard, link_settings, share_url};
+use profiles::ProfileAction;
+use state::{Action, Setting, State};
 
 mod components;
+mod fetch;
+mod link;
+mod profiles;
 mod state;
-mod yaixm;
+mod storage;
 
 // Callback data structures
 pub struct AirspaceSetting {
@@ -34,40 +46,150 @@ pub struct ExtraSetting {
     pub checked: bool,
 }
 
+pub struct LocalFile {
+    pub name: String,
+    pub data: Result<String, String>,
+}
+
+// YAIXM data loading state
+pub enum LoadState {
+    Loading,
+    Loaded(Box<Yaixm>, DataSource),
+    Failed(String),
+}
+
+// Trigger a "fake" download to save data
+fn download(filename: &str, data: &str) {
+    let blob = Blob::new(data);
+    let object_url = ObjectUrl::from(blob);
+
+    let download_anchor = document()
+        .get_element_by_id("download")
+        .expect("No document")
+        .dyn_into::<web_sys::HtmlAnchorElement>()
+        .expect("No anchor element");
+
+    download_anchor.set_download(filename);
+    download_anchor.set_href(&object_url);
+    download_anchor.click();
+}
+
 #[function_component(App)]
 fn app() -> Html {
-    let state = use_reducer(|| State {
-        settings: LocalStorage::get("settings").unwrap_or_default(),
-    });
-    let yaixm = use_state(|| None);
+    let link = use_state(link_settings);
+    let state = {
+        let link = link.clone();
+        use_reducer(move || State::load(link.as_ref().and_then(|l| l.as_ref().ok())))
+    };
+    let yaixm = use_state(|| LoadState::Loading);
+    let retry = use_state(|| 0);
+    let settings_error = use_state(|| None::<String>);
+    let local_error = use_state(|| None::<String>);
+    // Changed to redraw the settings forms, undoing a rejected selection
+    let form_key = use_state(|| 0);
+    let link_copied = use_state(|| false);
+    let pending_import = use_state(|| None::<(String, Settings)>);
 
-    // Fetch YAIXM data
+    // Fetch YAIXM data, falling back to the cached copy
     {
         let yaixm = yaixm.clone();
 
         use_effect_with_deps(
             move |_| {
                 wasm_bindgen_futures::spawn_local(async move {
-                    let data = fetch_yaixm().await;
-                    yaixm.set(data.ok());
+                    match fetch_yaixm().await {
+                        Ok(data) => {
+                            yaixm.set(LoadState::Loaded(Box::new(data), DataSource::Server))
+                        }
+                        Err(e) => {
+                            log::warn!("Can't fetch airspace data: {}", e);
+                            match cached_yaixm() {
+                                Some(data) => {
+                                    yaixm.set(LoadState::Loaded(Box::new(data), DataSource::Cache))
+                                }
+                                None => yaixm.set(LoadState::Failed(e.to_string())),
+                            }
+                        }
+                    }
                 });
                 || ()
             },
-            (),
+            *retry,
         );
     }
 
+    // Retry fetch callback
+    let onretry = {
+        let yaixm = yaixm.clone();
+        let retry = retry.clone();
+        Callback::from(move |_| {
+            yaixm.set(LoadState::Loading);
+            retry.set(*retry + 1);
+        })
+    };
+
+    // Local YAIXM file callback. A file that can't be loaded doesn't
+    // replace data that's already loaded
+    let onlocal = {
+        let yaixm = yaixm.clone();
+        let local_error = local_error.clone();
+        Callback::from(move |file: LocalFile| {
+            let data = file.data.and_then(|data| schema::from_str(&data));
+            match data {
+                Ok(data) => {
+                    local_error.set(None);
+                    yaixm.set(LoadState::Loaded(
+                        Box::new(data),
+                        DataSource::Local(file.name),
+                    ))
+                }
+                Err(e) => {
+                    let error = format!("{}: {}", file.name, e);
+                    match &*yaixm {
+                        LoadState::Loaded(..) => local_error.set(Some(error)),
+                        _ => yaixm.set(LoadState::Failed(error)),
+                    }
+                }
+            }
+        })
+    };
+
     // Airspace settings callback
     let onairspace_set = {
         let state = state.clone();
+        let settings_error = settings_error.clone();
+        let form_key = form_key.clone();
         Callback::from(move |setting: AirspaceSetting| {
-            state.dispatch(Action::Set {
-                name: setting.name,
-                value: setting.value,
-            })
+            match Setting::try_from(setting)
+                .and_then(|setting| setting.check(&state.settings).map(|_| setting))
+            {
+                Ok(setting) => {
+                    settings_error.set(None);
+                    state.dispatch(Action::Set(setting))
+                }
+                Err(e) => {
+                    settings_error.set(Some(e));
+                    form_key.set(*form_key + 1);
+                }
+            }
         })
     };
 
+    // Profile callback
+    let onprofile = {
+        let state = state.clone();
+        let settings_error = settings_error.clone();
+        Callback::from(
+            move |action: ProfileAction| match state.profiles.check(&action) {
+                Ok(()) => {
+                    settings_error.set(None);
+                    state.dispatch(Action::Profile(action))
+                }
+                Err(e) => settings_error.set(Some(e)),
+            },
+        )
+    };
+
     // RAT/LOA/Wave setting callback
     let onextra_set = {
         let state = state.clone();
@@ -103,29 +225,93 @@ fn app() -> Html {
         let state = state.clone();
         Callback::from(move |_| {
             // Save settings in local storage
-            LocalStorage::set("settings", &state.settings).ok();
-
-            // Create OpenAir data
-            let oa = openair(yaixm.as_ref().unwrap(), &state.settings);
-            let blob = Blob::new(oa.as_str());
-            let object_url = ObjectUrl::from(blob);
-
-            // Trigger a "fake" download to save the data
-            let download_anchor = document()
-                .get_element_by_id("download")
-                .expect("No document")
-                .dyn_into::<web_sys::HtmlAnchorElement>()
-                .expect("No anchor element");
-
-            download_anchor.set_href(&object_url);
-            download_anchor.click();
+            state.dispatch(Action::Save);
+
+            // Create airspace data
+            let LoadState::Loaded(yaixm, _) = &*yaixm else {
+                return;
+            };
+            let data = convert(yaixm, &state.settings, Utc::now());
+            download(state.settings.options.format.filename(), &data);
+        })
+    };
+
+    // Settings export callback
+    let onexport = {
+        let state = state.clone();
+        Callback::from(move |_| {
+            let data = serde_json::to_string_pretty(&state.settings).unwrap();
+            download("asselect-settings.json", &data);
         })
     };
 
-    // HTML rendering
-    let html_logic = match yaixm.as_ref() {
+    // Settings import callback, the import is previewed before applying
+    let onimport = {
+        let pending_import = pending_import.clone();
+        let settings_error = settings_error.clone();
+        Callback::from(move |file: LocalFile| {
+            match file.data.and_then(|data| Settings::from_json(&data)) {
+                Ok(settings) => {
+                    settings_error.set(None);
+                    pending_import.set(Some((file.name, settings)));
+                }
+                Err(e) => settings_error.set(Some(format!("{}: {}", file.name, e))),
+            }
+        })
+    };
+
+    let onimport_apply = {
+        let state = state.clone();
+        let pending_import = pending_import.clone();
+        Callback::from(move |_| {
+            if let Some((_, settings)) = &*pending_import {
+                state.dispatch(Action::Import(Box::new(settings.clone())));
+            }
+            pending_import.set(None);
+        })
+    };
+
+    let onimport_cancel = {
+        let pending_import = pending_import.clone();
+        Callback::from(move |_| pending_import.set(None))
+    };
+
+    // Copied link is out of date when settings change
+    {
+        let link_copied = link_copied.clone();
+        use_effect_with_deps(
+            move |_| {
+                link_copied.set(false);
+                || ()
+            },
+            state.settings.clone(),
+        );
+    }
+
+    // Copy settings link callback
+    let oncopy = {
+        let state = state.clone();
+        let settings_error = settings_error.clone();
+        let link_copied = link_copied.clone();
+        Callback::from(move |_| {
+            let url = share_url(&state.settings);
+            let settings_error = settings_error.clone();
+            let link_copied = link_copied.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match copy_to_clipboard(&url).await {
+                    Ok(()) => link_copied.set(true),
+                    Err(e) => settings_error.set(Some(e)),
+                }
+            });
+        })
+    };
+
+    // HTML rendering. Yew's html! macro expands component props to
+    // statements that clippy reports as unnecessary operations
+    #[allow(clippy::unnecessary_operation)]
+    let html_logic = match &*yaixm {
         // Render full interface if YAIXM data is available
-        Some(yaixm) => {
+        LoadState::Loaded(yaixm, source) => {
             let mut gliding_sites = gliding_sites(yaixm);
             gliding_sites.sort();
 
@@ -145,11 +331,136 @@ fn app() -> Html {
                 "Wave Boxes".to_string(),
             ];
 
+            let airac_date = yaixm
+                .release
+                .airac_date
+                .get(..10)
+                .unwrap_or(&yaixm.release.airac_date);
+            // AIRAC cycle of the data
+            let airac_status = airac::status(&yaixm.release.airac_date, Utc::now().date_naive());
+            let airac_info = match &airac_status {
+                Ok(status) => format!(
+                    "AIRAC {} ({}), next cycle starts {}",
+                    status.data.ident(),
+                    status.data.start(),
+                    status.current.next().start()
+                ),
+                Err(e) => e.clone(),
+            };
+            let airac_notice = match airac_status {
+                Ok(status) if status.validity == Validity::Expired => html! {
+                  <div class="container block">
+                    <div class="notification is-danger mx-4">
+                      {format!(
+                        "This airspace data is for AIRAC cycle {}, which has expired. The current cycle, {}, started on {}.",
+                        status.data.ident(),
+                        status.current.ident(),
+                        status.current.start()
+                      )}
+                    </div>
+                  </div>
+                },
+                Ok(status) if status.validity == Validity::Future => html! {
+                  <div class="container block">
+                    <div class="notification is-info mx-4">
+                      {format!(
+                        "This airspace data is for AIRAC cycle {}, which doesn't start until {}.",
+                        status.data.ident(),
+                        status.data.start()
+                      )}
+                    </div>
+                  </div>
+                },
+                _ => html! {},
+            };
+
+            let title = match source {
+                DataSource::Local(_) => "ASSelect - UK Airspace (custom dataset)",
+                _ => "ASSelect - UK Airspace",
+            };
+            // Show where the data came from, unless it is the server
+            let source_notice = match source {
+                DataSource::Server => html! {},
+                DataSource::Cache => html! {
+                  <div class="container block">
+                    <div class="notification is-warning mx-4">
+                      {format!(
+                        "Can't reach the server, using saved airspace data for AIRAC {}",
+                        airac_date
+                      )}
+                    </div>
+                  </div>
+                },
+                DataSource::Local(name) => html! {
+                  <div class="container block">
+                    <div class="notification is-info mx-4">
+                      <p><strong>{format!("Custom dataset: {}", name)}</strong></p>
+                      <p>{&yaixm.release.note}</p>
+                      <p>{format!("AIRAC: {}, Commit: {}", airac_date, yaixm.release.commit)}</p>
+                      <button class="button is-small is-info is-light mt-2" onclick={onretry.clone()}>
+                        {"Use server data"}
+                      </button>
+                    </div>
+                  </div>
+                },
+            };
+
+            // Problems with settings from a shared link
+            let link_notice = match &*link {
+                Some(Err(e)) => html! {
+                  <div class="container block">
+                    <div class="notification is-danger is-light mx-4">
+                      {format!("Can't use settings link: {}", e)}
+                    </div>
+                  </div>
+                },
+                Some(Ok(settings)) => {
+                    let unknown = settings.unknown_names(yaixm);
+                    html! {
+                      <>
+                      if state.settings.profile.is_none() {
+                        <div class="container block">
+                          <div class="notification is-info is-light mx-4">
+                            {"Using settings from a link. Save them to keep them as a new profile."}
+                          </div>
+                        </div>
+                      }
+                      if !unknown.is_empty() {
+                        <div class="container block">
+                          <div class="notification is-warning is-light mx-4">
+                            {format!(
+                              "Settings link includes names not in the airspace data: {}",
+                              unknown.join(", ")
+                            )}
+                          </div>
+                        </div>
+                      }
+                      </>
+                    }
+                }
+                None => html! {},
+            };
+
+            let import_preview = match &*pending_import {
+                Some((name, settings)) => html! {
+                  <div class="container block">
+                    <ImportPreview
+                      name={name.clone()}
+                      changes={state.settings.changes(settings)}
+                      unknown={settings.unknown_names(yaixm)}
+                      onapply={onimport_apply.clone()}
+                      oncancel={onimport_cancel.clone()} />
+                  </div>
+                },
+                None => html! {},
+            };
+
             let tab_names = vec![
                 "Main".to_string(),
                 "Options".to_string(),
                 "Extra".to_string(),
                 "NOTAM".to_string(),
+                "What's New".to_string(),
             ];
 
             html! {
@@ -158,30 +469,77 @@ fn app() -> Html {
                   <div class="hero-body">
                     <div class="container">
                       <div class="title is-4">
-                        {"ASSelect - UK Airspace"}
+                        {title}
+                      </div>
+                      <div class="subtitle is-6">
+                        {airac_info}
                       </div>
                     </div>
                   </div>
                 </header>
 
+                {source_notice}
+                {airac_notice}
+                {link_notice}
+                if let Some(error) = &state.storage_error {
+                  <div class="container block">
+                    <div class="notification is-warning mx-4">
+                      {error}
+                    </div>
+                  </div>
+                }
+
+                <div class="container block">
+                  <ProfileBar names={state.profiles.names()} current={state.profiles.current} callback={onprofile} />
+                </div>
+
                 <div class="container block">
                   <Tabs {tab_names}>
-                    <AirspaceTab settings={state.settings.airspace.clone()} options={state.settings.options.clone()} {gliding_sites} callback={onairspace_set.clone()} />
-                    <OptionsTab settings={state.settings.airspace.clone()} options={state.settings.options.clone()} callback={onairspace_set.clone()} />
+                    <AirspaceTab key={*form_key} settings={state.settings.airspace.clone()} options={state.settings.options.clone()} {gliding_sites} callback={onairspace_set.clone()} />
+                    <OptionsTab key={*form_key} settings={state.settings.airspace.clone()} options={state.settings.options.clone()} callback={onairspace_set.clone()} />
                     <ExtraTab names={extra_names} categories={vec![ExtraCategory::Rat, ExtraCategory::Loa, ExtraCategory::Wave]} on_clear={onextra_clear.clone()}>
                       <ExtraPanel category={ExtraCategory::Rat} names={rat_names} selected={rat_selected} callback={onextra_set.clone()}/>
                       <ExtraPanel category={ExtraCategory::Loa} names={loa_names} selected={loa_selected} callback={onextra_set.clone()}/>
                       <ExtraPanel category={ExtraCategory::Wave} names={wav_names} selected={wav_selected} callback={onextra_set.clone()}/>
                     </ExtraTab>
                     <NotamTab />
+                    <WhatsNewTab changes={release_changes()} />
                   </Tabs>
                 </div>
 
+                if let Some(error) = &*settings_error {
+                  <div class="container block">
+                    <div class="notification is-danger is-light mx-4">
+                      {error}
+                    </div>
+                  </div>
+                }
+                if let Some(error) = &*local_error {
+                  <div class="container block">
+                    <div class="notification is-danger is-light mx-4">
+                      {format!("Can't load airspace file {}", error)}
+                    </div>
+                  </div>
+                }
+                {import_preview}
+
                 <div class="container block">
                   <div class="mx-4">
                     <button class="button is-primary" onclick={onsave}>
                       {"Save"}
                     </button>
+                    <button class="button ml-2" onclick={oncopy}>
+                      {if *link_copied { "Link copied" } else { "Copy link" }}
+                    </button>
+                    <span class="is-inline-block ml-2">
+                      <FilePicker label={"Load YAIXM file..."} callback={onlocal.clone()}/>
+                    </span>
+                    <button class="button ml-2" onclick={onexport}>
+                      {"Export settings"}
+                    </button>
+                    <span class="is-inline-block ml-2">
+                      <FilePicker label={"Import settings..."} callback={onimport}/>
+                    </span>
                     <a class="button is-text is-pulled-right">
                       {"Help"}
                     </a>
@@ -192,8 +550,36 @@ fn app() -> Html {
                 </>
             }
         }
+        // Failed to get YAIXM data
+        LoadState::Failed(error) => {
+            html! {
+              <>
+              <header class="hero is-small is-danger block">
+                <div class="hero-body">
+                  <div class="container">
+                    <div class="title is-4">
+                      {"ASSelect - Can't get airspace data"}
+                    </div>
+                  </div>
+                </div>
+              </header>
+
+              <div class="container block">
+                <div class="notification is-danger is-light mx-4">
+                  {error}
+                </div>
+                <div class="buttons mx-4">
+                  <button class="button is-primary" onclick={onretry}>
+                    {"Retry"}
+                  </button>
+                  <FilePicker label={"Load local YAIXM file..."} callback={onlocal}/>
+                </div>
+              </div>
+              </>
+            }
+        }
         // Waiting for YAIXM data
-        None => {
+        LoadState::Loading => {
             html! {
               <header class="hero is-small is-primary">
                 <div class="hero-body">
@@ -0,0 +1,246 @@
+use crate::storage;
+use asselect::settings::{Profile, Settings};
+use gloo_storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+
+// Local storage keys for profiles, settings saved before profiles, and
+// stored data that couldn't be loaded
+const PROFILES_KEY: &str = "profiles";
+const SETTINGS_KEY: &str = "settings";
+const BAD_PROFILES_KEY: &str = "profiles_bad";
+
+const DEFAULT_PROFILE: &str = "Default";
+const LINK_PROFILE: &str = "Shared link";
+
+// Profile changes
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProfileAction {
+    Create(String),
+    Rename(String),
+    Duplicate(String),
+    Delete,
+    Switch(usize),
+}
+
+// Named settings profiles, one of which is in use
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Profiles {
+    pub current: usize,
+    pub profiles: Vec<Profile>,
+}
+
+impl Default for Profiles {
+    fn default() -> Self {
+        Profiles::new(&Settings::default())
+    }
+}
+
+impl Profiles {
+    fn new(settings: &Settings) -> Self {
+        Profiles {
+            current: 0,
+            profiles: vec![Profile::new(DEFAULT_PROFILE, settings)],
+        }
+    }
+
+    // Profiles from local storage, migrated from any older version. Data
+    // that can't be fully loaded is kept aside, and any profiles that could
+    // be loaded used, or else a default profile
+    pub fn load() -> (Self, Option<String>) {
+        let storage = LocalStorage::raw();
+        let data = [PROFILES_KEY, SETTINGS_KEY]
+            .iter()
+            .find_map(|key| storage.get_item(key).ok().flatten());
+        let Some(data) = data else {
+            return (Profiles::default(), None);
+        };
+
+        let (profiles, error) = match storage::from_str(&data) {
+            Ok((profiles, errors)) if errors.is_empty() => return (profiles, None),
+            Ok((profiles, errors)) => (
+                profiles,
+                format!(
+                    "Some of your saved profiles couldn't be loaded ({}), so they've been left out.",
+                    errors.join("; ")
+                ),
+            ),
+            Err(e) => (
+                Profiles::default(),
+                format!(
+                    "Your saved settings couldn't be loaded ({}), so defaults are being used.",
+                    e
+                ),
+            ),
+        };
+        log::warn!("{}", error);
+        let error = match storage.set_item(BAD_PROFILES_KEY, &data) {
+            Ok(()) => format!(
+                "{} The old settings have been kept in browser storage as \"{}\".",
+                error, BAD_PROFILES_KEY
+            ),
+            Err(_) => error,
+        };
+        (profiles, Some(error))
+    }
+
+    pub fn store(&self) {
+        if let Err(e) = LocalStorage::raw().set_item(PROFILES_KEY, &storage::to_string(self)) {
+            log::warn!("Can't save profiles: {:?}", e);
+        }
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.profiles.iter().map(|p| p.name.clone()).collect()
+    }
+
+    // Settings of the current profile
+    pub fn settings(&self) -> Settings {
+        self.profiles[self.current].settings()
+    }
+
+    // Save settings to the current profile
+    pub fn update(&mut self, settings: &Settings) {
+        let profile = &mut self.profiles[self.current];
+        *profile = Profile::new(&profile.name, settings);
+    }
+
+    // Save settings, adding a profile for settings from a shared link
+    pub fn save(&mut self, settings: &Settings) {
+        if settings.profile.is_none() {
+            let name = (1..)
+                .map(|n| match n {
+                    1 => LINK_PROFILE.to_string(),
+                    n => format!("{} {}", LINK_PROFILE, n),
+                })
+                .find(|name| self.check_name(name).is_ok())
+                .unwrap();
+            self.apply(ProfileAction::Create(name));
+        }
+        self.update(settings);
+    }
+
+    fn check_name(&self, name: &str) -> Result<(), String> {
+        if name.trim().is_empty() {
+            Err("Profile name can't be blank".to_string())
+        } else if self.profiles.iter().any(|p| p.name == name.trim()) {
+            Err(format!(
+                "There is already a profile called \"{}\"",
+                name.trim()
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    // Check action can be applied
+    pub fn check(&self, action: &ProfileAction) -> Result<(), String> {
+        match action {
+            ProfileAction::Create(name)
+            | ProfileAction::Rename(name)
+            | ProfileAction::Duplicate(name) => self.check_name(name),
+            ProfileAction::Delete if self.profiles.len() == 1 => {
+                Err("Can't delete the only profile".to_string())
+            }
+            ProfileAction::Delete => Ok(()),
+            ProfileAction::Switch(index) if *index >= self.profiles.len() => {
+                Err("No such profile".to_string())
+            }
+            ProfileAction::Switch(_) => Ok(()),
+        }
+    }
+
+    // Apply a checked action, the new profile becomes current
+    pub fn apply(&mut self, action: ProfileAction) {
+        if self.check(&action).is_err() {
+            return;
+        }
+
+        match action {
+            ProfileAction::Create(name) => {
+                self.profiles
+                    .push(Profile::new(name.trim(), &Settings::default()));
+                self.current = self.profiles.len() - 1;
+            }
+            ProfileAction::Rename(name) => {
+                self.profiles[self.current].name = name.trim().to_string();
+            }
+            ProfileAction::Duplicate(name) => {
+                let settings = self.profiles[self.current].settings.clone();
+                self.profiles.push(Profile::new(name.trim(), &settings));
+                self.current = self.profiles.len() - 1;
+            }
+            ProfileAction::Delete => {
+                self.profiles.remove(self.current);
+                self.current = self.current.min(self.profiles.len() - 1);
+            }
+            ProfileAction::Switch(index) => self.current = index,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actions() {
+        let mut profiles = Profiles::default();
+        let mut settings = profiles.settings();
+        assert_eq!(settings.profile.as_deref(), Some("Default"));
+
+        settings.options.max_level = 195;
+        profiles.update(&settings);
+        profiles.apply(ProfileAction::Duplicate(" Competition ".to_string()));
+        profiles.apply(ProfileAction::Create("North".to_string()));
+        assert_eq!(profiles.names(), ["Default", "Competition", "North"]);
+        assert_eq!(profiles.current, 2);
+        assert_eq!(profiles.settings().options.max_level, 600);
+
+        profiles.apply(ProfileAction::Switch(1));
+        assert_eq!(profiles.settings().options.max_level, 195);
+        assert_eq!(profiles.settings().profile.as_deref(), Some("Competition"));
+
+        profiles.apply(ProfileAction::Rename("Comp".to_string()));
+        profiles.apply(ProfileAction::Delete);
+        assert_eq!(profiles.names(), ["Default", "North"]);
+        assert_eq!(profiles.current, 1);
+
+        profiles.apply(ProfileAction::Delete);
+        assert_eq!(
+            profiles.check(&ProfileAction::Delete),
+            Err("Can't delete the only profile".to_string())
+        );
+        assert_eq!(
+            profiles.check(&ProfileAction::Create("Default".to_string())),
+            Err("There is already a profile called \"Default\"".to_string())
+        );
+        assert_eq!(
+            profiles.check(&ProfileAction::Rename(" ".to_string())),
+            Err("Profile name can't be blank".to_string())
+        );
+        assert!(profiles.check(&ProfileAction::Switch(1)).is_err());
+    }
+
+    #[test]
+    fn save() {
+        let mut profiles = Profiles::default();
+        let mut settings = Settings::default();
+        settings.options.max_level = 105;
+
+        // Settings not from a profile are saved to a new one
+        for _ in 0..2 {
+            profiles.save(&settings);
+        }
+        assert_eq!(
+            profiles.names(),
+            ["Default", "Shared link", "Shared link 2"]
+        );
+        assert_eq!(profiles.profiles[0].settings, Settings::default());
+
+        let mut settings = profiles.settings();
+        settings.options.max_level = 65;
+        profiles.save(&settings);
+        assert_eq!(profiles.names().len(), 3);
+        assert_eq!(profiles.settings().options.max_level, 65);
+    }
+}
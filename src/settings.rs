@@ -0,0 +1,485 @@
+use crate::task::Task;
+use crate::yaixm::util::{gliding_sites, loa_names, rat_names, wav_names};
+use crate::yaixm::Yaixm;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+// Airspace types
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum AirType {
+    ClassA,
+    ClassB,
+    ClassC,
+    ClassD,
+    ClassE,
+    ClassF,
+    ClassG,
+    Danger,
+    Cta,
+    Ctr,
+    Gliding,
+    Matz,
+    Other,
+    Prohibited,
+    Restricted,
+    Rmz,
+    Tmz,
+}
+
+// Output format
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Format {
+    OpenAir,
+    RatOnly,
+    Competition,
+    GeoJson,
+    Tnp,
+    Kml,
+}
+
+impl Format {
+    // Name of the generated file
+    pub fn filename(&self) -> &'static str {
+        match self {
+            Format::GeoJson => "airspace.geojson",
+            Format::Tnp => "airspace.sua",
+            Format::Kml => "airspace.kml",
+            _ => "openair.txt",
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Format::OpenAir => "openair",
+            Format::RatOnly => "ratonly",
+            Format::Competition => "competition",
+            Format::GeoJson => "geojson",
+            Format::Tnp => "tnp",
+            Format::Kml => "kml",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "openair" => Ok(Format::OpenAir),
+            "ratonly" => Ok(Format::RatOnly),
+            "competition" => Ok(Format::Competition),
+            "geojson" => Ok(Format::GeoJson),
+            "tnp" => Ok(Format::Tnp),
+            "kml" => Ok(Format::Kml),
+            _ => Err(format!("unknown format \"{}\"", value)),
+        }
+    }
+}
+
+// Airspace settings. Missing fields take their default value, so fields
+// can be added without migrating stored settings
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Airspace {
+    pub atz: AirType,
+    pub ils: Option<AirType>,
+    pub unlicensed: Option<AirType>,
+    pub microlight: Option<AirType>,
+    pub gliding: Option<AirType>,
+    pub home: Option<String>,
+    pub hirta_gvs: Option<AirType>,
+    pub obstacle: bool,
+}
+
+impl Default for Airspace {
+    fn default() -> Self {
+        Airspace {
+            atz: AirType::Ctr,
+            ils: None,
+            unlicensed: None,
+            microlight: None,
+            gliding: None,
+            home: None,
+            hirta_gvs: None,
+            obstacle: false,
+        }
+    }
+}
+
+// Latitude limits that meant "None" in settings saved before there were
+// profiles, remapped by the stored settings migration
+pub const OLD_NORTH_NONE: f64 = 59.0;
+pub const OLD_SOUTH_NONE: f64 = 49.0;
+
+// Additional options, missing fields take their default value
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Options {
+    pub max_level: u16,
+    pub radio: bool,
+    pub north: f64,
+    pub south: f64,
+    pub format: Format,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            max_level: 600,
+            radio: false,
+            north: 90.0,
+            south: -90.0,
+            format: Format::OpenAir,
+        }
+    }
+}
+
+// Application settings. New fields need #[serde(default)], otherwise
+// settings stored by the web app need a migration to add them
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    pub airspace: Airspace,
+    pub options: Options,
+    pub loa: HashSet<String>,
+    pub rat: HashSet<String>,
+    pub wave: HashSet<String>,
+    // Only include airspace near the competition task
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<Task>,
+    // Name of the profile the settings came from, noted in the output
+    #[serde(skip)]
+    pub profile: Option<String>,
+}
+
+// Check an optional airspace type is one of those offered
+fn check_type(name: &str, value: Option<AirType>, allowed: &[AirType]) -> Result<(), String> {
+    match value {
+        Some(air_type) if !allowed.contains(&air_type) => {
+            Err(format!("{:?} isn't allowed for {}", air_type, name))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Airspace type for display
+fn type_text(value: Option<AirType>) -> String {
+    value.map_or("Exclude".to_string(), |t| format!("{:?}", t))
+}
+
+fn task_text(task: &Option<Task>) -> String {
+    match task {
+        Some(task) => {
+            let names: Vec<&str> = task.turnpoints.iter().map(|t| t.name.as_str()).collect();
+            format!("{} ({} buffer)", names.join(" - "), task.buffer)
+        }
+        None => "None".to_string(),
+    }
+}
+
+impl Settings {
+    // Parse plain settings JSON or named profile JSON
+    pub fn from_json(text: &str) -> Result<Settings, String> {
+        let value: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        let settings = if value.get("settings").is_some() {
+            let profile: Profile = serde_json::from_value(value).map_err(|e| e.to_string())?;
+            profile.settings()
+        } else {
+            serde_json::from_value(value).map_err(|e| e.to_string())?
+        };
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    // Check values are in range and airspace types are sensible
+    pub fn validate(&self) -> Result<(), String> {
+        use AirType::*;
+
+        let air = &self.airspace;
+        check_type("atz", Some(air.atz), &[ClassD, Ctr])?;
+        check_type("ils", air.ils, &[ClassF, ClassG])?;
+        check_type("unlicensed", air.unlicensed, &[ClassF, ClassG])?;
+        check_type("microlight", air.microlight, &[ClassF, ClassG])?;
+        check_type("gliding", air.gliding, &[Gliding, ClassF, ClassG])?;
+        check_type("hirta_gvs", air.hirta_gvs, &[Danger, Restricted])?;
+
+        let opts = &self.options;
+        for (name, lat) in [("north", opts.north), ("south", opts.south)] {
+            if !(-90.0..=90.0).contains(&lat) {
+                return Err(format!("{} latitude {} is out of range", name, lat));
+            }
+        }
+        if opts.south >= opts.north {
+            return Err(format!(
+                "south latitude {} isn't south of north latitude {}",
+                opts.south, opts.north
+            ));
+        }
+        if opts.max_level == 0 {
+            return Err("maximum level must be above zero".to_string());
+        }
+        Ok(())
+    }
+
+    // Description of the differences from other settings
+    pub fn changes(&self, new: &Settings) -> Vec<String> {
+        let mut changes = Vec::new();
+        let mut check = |name: &str, old: String, new: String| {
+            if old != new {
+                changes.push(format!("{}: {} \u{2192} {}", name, old, new));
+            }
+        };
+
+        let (old_air, new_air) = (&self.airspace, &new.airspace);
+        check(
+            "ATZ",
+            type_text(Some(old_air.atz)),
+            type_text(Some(new_air.atz)),
+        );
+        check("ILS", type_text(old_air.ils), type_text(new_air.ils));
+        check(
+            "Unlicensed airfield",
+            type_text(old_air.unlicensed),
+            type_text(new_air.unlicensed),
+        );
+        check(
+            "Microlight airfield",
+            type_text(old_air.microlight),
+            type_text(new_air.microlight),
+        );
+        check(
+            "Gliding airfield",
+            type_text(old_air.gliding),
+            type_text(new_air.gliding),
+        );
+        check(
+            "Home airfield",
+            old_air.home.clone().unwrap_or("None".to_string()),
+            new_air.home.clone().unwrap_or("None".to_string()),
+        );
+        check(
+            "HIRTA/GVS",
+            type_text(old_air.hirta_gvs),
+            type_text(new_air.hirta_gvs),
+        );
+        check(
+            "Obstacle",
+            old_air.obstacle.to_string(),
+            new_air.obstacle.to_string(),
+        );
+
+        let (old_opts, new_opts) = (&self.options, &new.options);
+        check(
+            "Format",
+            old_opts.format.to_string(),
+            new_opts.format.to_string(),
+        );
+        check(
+            "Maximum level",
+            old_opts.max_level.to_string(),
+            new_opts.max_level.to_string(),
+        );
+        check(
+            "Append frequency",
+            old_opts.radio.to_string(),
+            new_opts.radio.to_string(),
+        );
+        check(
+            "Omit north of",
+            old_opts.north.to_string(),
+            new_opts.north.to_string(),
+        );
+        check(
+            "Omit south of",
+            old_opts.south.to_string(),
+            new_opts.south.to_string(),
+        );
+        check("Task", task_text(&self.task), task_text(&new.task));
+
+        for (kind, old, new) in [
+            ("RA(T)", &self.rat, &new.rat),
+            ("LOA", &self.loa, &new.loa),
+            ("Wave box", &self.wave, &new.wave),
+        ] {
+            let mut added: Vec<&String> = new.difference(old).collect();
+            added.sort();
+            let mut removed: Vec<&String> = old.difference(new).collect();
+            removed.sort();
+            changes.extend(added.iter().map(|n| format!("{} added: {}", kind, n)));
+            changes.extend(removed.iter().map(|n| format!("{} removed: {}", kind, n)));
+        }
+        changes
+    }
+
+    // Home airfield, RAT, LOA and wave box names not in the YAIXM data
+    pub fn unknown_names(&self, yaixm: &Yaixm) -> Vec<String> {
+        let missing = |kind: &str, names: Vec<&String>, known: Vec<String>| {
+            let mut missing: Vec<String> = names
+                .into_iter()
+                .filter(|n| !known.contains(n))
+                .map(|n| format!("{} \"{}\"", kind, n))
+                .collect();
+            missing.sort();
+            missing
+        };
+
+        let mut unknown = missing(
+            "home airfield",
+            self.airspace.home.iter().collect(),
+            gliding_sites(yaixm),
+        );
+        unknown.extend(missing(
+            "RA(T)",
+            self.rat.iter().collect(),
+            rat_names(yaixm),
+        ));
+        unknown.extend(missing("LOA", self.loa.iter().collect(), loa_names(yaixm)));
+        unknown.extend(missing(
+            "wave box",
+            self.wave.iter().collect(),
+            wav_names(yaixm),
+        ));
+        unknown
+    }
+}
+
+// Named settings
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub name: String,
+    pub settings: Settings,
+}
+
+impl Profile {
+    pub fn new(name: &str, settings: &Settings) -> Self {
+        Profile {
+            name: name.to_string(),
+            settings: Settings {
+                profile: None,
+                ..settings.clone()
+            },
+        }
+    }
+
+    // Profile settings, tagged with the profile name
+    pub fn settings(&self) -> Settings {
+        Settings {
+            profile: Some(self.name.clone()),
+            ..self.settings.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_json() {
+        let mut settings = Settings::default();
+        settings.options.max_level = 195;
+        settings.rat.insert("ROYAL FLIGHT".to_string());
+
+        let profile = Profile::new("Competition", &settings);
+        let json = serde_json::to_string(&profile).unwrap();
+        assert!(json.starts_with(r#"{"name":"Competition","settings":{"airspace":"#));
+
+        let loaded = Settings::from_json(&json).unwrap();
+        assert_eq!(loaded.profile.as_deref(), Some("Competition"));
+        assert_eq!(loaded.options.max_level, 195);
+        assert_eq!(loaded, profile.settings());
+
+        let plain = serde_json::to_string(&settings).unwrap();
+        assert_eq!(Settings::from_json(&plain).unwrap(), settings);
+
+        let err = Settings::from_json(r#"{"name": "Club", "settings": {}}"#).unwrap_err();
+        assert!(err.starts_with("missing field"), "{}", err);
+    }
+
+    #[test]
+    fn latitude_limits() {
+        // Only old stored settings are remapped, explicit limits are kept
+        let mut value = serde_json::to_value(Settings::default()).unwrap();
+        value["options"]["north"] = OLD_NORTH_NONE.into();
+        value["options"]["south"] = OLD_SOUTH_NONE.into();
+        let settings: Settings = serde_json::from_value(value).unwrap();
+        assert_eq!(
+            (settings.options.north, settings.options.south),
+            (59.0, 49.0)
+        );
+    }
+
+    #[test]
+    fn validation() {
+        let json = |f: fn(&mut Value)| {
+            let mut value = serde_json::to_value(Settings::default()).unwrap();
+            f(&mut value);
+            Settings::from_json(&value.to_string())
+        };
+
+        assert!(json(|_| ()).is_ok());
+        assert_eq!(
+            json(|v| v["airspace"]["atz"] = "Danger".into()).unwrap_err(),
+            "Danger isn't allowed for atz"
+        );
+        assert_eq!(
+            json(|v| v["options"]["north"] = 95.0.into()).unwrap_err(),
+            "north latitude 95 is out of range"
+        );
+        assert_eq!(
+            json(|v| v["options"]["south"] = 90.0.into()).unwrap_err(),
+            "south latitude 90 isn't south of north latitude 90"
+        );
+        let err = json(|v| v["options"]["colour"] = "red".into()).unwrap_err();
+        assert!(err.starts_with("unknown field `colour`"), "{}", err);
+        let err = json(|v| v["options"]["max_level"] = "FL100".into()).unwrap_err();
+        assert!(err.starts_with("invalid type"), "{}", err);
+    }
+
+    #[test]
+    fn changes() {
+        let old = Settings::default();
+        let mut new = old.clone();
+        new.options.max_level = 195;
+        new.airspace.gliding = Some(AirType::Gliding);
+        new.rat.insert("ROYAL FLIGHT".to_string());
+        let mut old_rat = old.clone();
+        old_rat.rat.insert("AIR SHOW".to_string());
+
+        assert!(old.changes(&old).is_empty());
+        assert_eq!(
+            old_rat.changes(&new),
+            [
+                "Gliding airfield: Exclude \u{2192} Gliding",
+                "Maximum level: 600 \u{2192} 195",
+                "RA(T) added: ROYAL FLIGHT",
+                "RA(T) removed: AIR SHOW"
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_names() {
+        let mut settings = Settings::default();
+        settings.rat.insert("ROYAL FLIGHT".to_string());
+        settings.rat.insert("OLD DISPLAY".to_string());
+        settings.loa.insert("NO SUCH LOA".to_string());
+        settings.airspace.home = Some("NOWHERE".to_string());
+
+        assert_eq!(
+            settings.unknown_names(&crate::yaixm::testdata::yaixm()),
+            [
+                "home airfield \"NOWHERE\"",
+                "RA(T) \"OLD DISPLAY\"",
+                "LOA \"NO SUCH LOA\""
+            ]
+        );
+    }
+}
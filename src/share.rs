@@ -0,0 +1,260 @@
+//! Compact, versioned encoding of settings for sharing as a URL fragment.
+//!
+//! The fragment is a list of `key=value` pairs separated by `&`, starting
+//! with the version, e.g. `v=1&fmt=competition&max=195&rat=ROYAL%20FLIGHT`.
+//! Settings with their default value are omitted, and names are percent
+//! encoded. Name lists are comma separated.
+
+use crate::settings::{AirType, Settings};
+use std::collections::HashSet;
+
+pub const VERSION: u32 = 1;
+
+// Airspace type codes, "x" is excluded
+fn air_type_code(air_type: Option<AirType>) -> &'static str {
+    match air_type {
+        Some(AirType::ClassD) => "classd",
+        Some(AirType::ClassF) => "classf",
+        Some(AirType::ClassG) => "classg",
+        Some(AirType::Ctr) => "ctr",
+        Some(AirType::Danger) => "danger",
+        Some(AirType::Gliding) => "gsec",
+        Some(AirType::Restricted) => "restricted",
+        _ => "x",
+    }
+}
+
+fn parse_air_type(code: &str) -> Result<Option<AirType>, String> {
+    match code {
+        "classd" => Ok(Some(AirType::ClassD)),
+        "classf" => Ok(Some(AirType::ClassF)),
+        "classg" => Ok(Some(AirType::ClassG)),
+        "ctr" => Ok(Some(AirType::Ctr)),
+        "danger" => Ok(Some(AirType::Danger)),
+        "gsec" => Ok(Some(AirType::Gliding)),
+        "restricted" => Ok(Some(AirType::Restricted)),
+        "x" => Ok(None),
+        _ => Err(format!("unknown airspace type \"{}\"", code)),
+    }
+}
+
+fn parse_flag(value: &str) -> Result<bool, String> {
+    match value {
+        "1" => Ok(true),
+        "0" => Ok(false),
+        _ => Err(format!("invalid flag \"{}\"", value)),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse::<T>()
+        .map_err(|_| format!("invalid number \"{}\"", value))
+}
+
+// Percent encode everything except unreserved characters
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn percent_decode(text: &str) -> Result<String, String> {
+    let invalid = || format!("invalid encoding \"{}\"", text);
+
+    let mut bytes = Vec::new();
+    let mut iter = text.bytes();
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = [
+                iter.next().ok_or_else(invalid)?,
+                iter.next().ok_or_else(invalid)?,
+            ];
+            let hex = std::str::from_utf8(&hex).map_err(|_| invalid())?;
+            bytes.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+        } else {
+            bytes.push(b);
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| invalid())
+}
+
+fn encode_names(names: &HashSet<String>) -> String {
+    let mut names: Vec<String> = names.iter().map(|n| percent_encode(n)).collect();
+    names.sort();
+    names.join(",")
+}
+
+fn decode_names(value: &str) -> Result<HashSet<String>, String> {
+    value
+        .split(',')
+        .filter(|n| !n.is_empty())
+        .map(percent_decode)
+        .collect()
+}
+
+// Settings fragment, without the leading '#'
+pub fn encode(settings: &Settings) -> String {
+    let default = Settings::default();
+    let (air, def_air) = (&settings.airspace, &default.airspace);
+    let (opts, def_opts) = (&settings.options, &default.options);
+
+    let mut pairs = vec![format!("v={}", VERSION)];
+    let mut add = |key: &str, value: String, default: String| {
+        if value != default {
+            pairs.push(format!("{}={}", key, value));
+        }
+    };
+
+    add("fmt", opts.format.to_string(), def_opts.format.to_string());
+    add(
+        "max",
+        opts.max_level.to_string(),
+        def_opts.max_level.to_string(),
+    );
+    add("n", opts.north.to_string(), def_opts.north.to_string());
+    add("s", opts.south.to_string(), def_opts.south.to_string());
+    add(
+        "radio",
+        (opts.radio as u8).to_string(),
+        (def_opts.radio as u8).to_string(),
+    );
+
+    let air_types = [
+        ("atz", Some(air.atz), Some(def_air.atz)),
+        ("ils", air.ils, def_air.ils),
+        ("unl", air.unlicensed, def_air.unlicensed),
+        ("mic", air.microlight, def_air.microlight),
+        ("gli", air.gliding, def_air.gliding),
+        ("hg", air.hirta_gvs, def_air.hirta_gvs),
+    ];
+    for (key, value, default) in air_types {
+        add(
+            key,
+            air_type_code(value).to_string(),
+            air_type_code(default).to_string(),
+        );
+    }
+    add(
+        "obs",
+        (air.obstacle as u8).to_string(),
+        (def_air.obstacle as u8).to_string(),
+    );
+    if let Some(home) = &air.home {
+        add("home", percent_encode(home), String::new());
+    }
+
+    add("rat", encode_names(&settings.rat), String::new());
+    add("loa", encode_names(&settings.loa), String::new());
+    add("wave", encode_names(&settings.wave), String::new());
+
+    pairs.join("&")
+}
+
+// Settings from fragment, with or without the leading '#'
+pub fn decode(fragment: &str) -> Result<Settings, String> {
+    let fragment = fragment.strip_prefix('#').unwrap_or(fragment);
+    let mut pairs = fragment.split('&').map(|pair| {
+        pair.split_once('=')
+            .ok_or_else(|| format!("invalid setting \"{}\"", pair))
+    });
+
+    match pairs.next().transpose()? {
+        Some(("v", version)) => {
+            let version: u32 = parse_number(version)?;
+            if version != VERSION {
+                return Err(format!("unsupported settings link version {}", version));
+            }
+        }
+        _ => return Err("missing settings link version".to_string()),
+    }
+
+    let mut settings = Settings::default();
+    let air = &mut settings.airspace;
+    let opts = &mut settings.options;
+    for pair in pairs {
+        let (key, value) = pair?;
+        match key {
+            "fmt" => opts.format = value.parse()?,
+            "max" => opts.max_level = parse_number(value)?,
+            "n" => opts.north = parse_number(value)?,
+            "s" => opts.south = parse_number(value)?,
+            "radio" => opts.radio = parse_flag(value)?,
+            "atz" => {
+                air.atz = parse_air_type(value)?
+                    .ok_or_else(|| format!("invalid ATZ type \"{}\"", value))?
+            }
+            "ils" => air.ils = parse_air_type(value)?,
+            "unl" => air.unlicensed = parse_air_type(value)?,
+            "mic" => air.microlight = parse_air_type(value)?,
+            "gli" => air.gliding = parse_air_type(value)?,
+            "hg" => air.hirta_gvs = parse_air_type(value)?,
+            "obs" => air.obstacle = parse_flag(value)?,
+            "home" => air.home = Some(percent_decode(value)?),
+            "rat" => settings.rat = decode_names(value)?,
+            "loa" => settings.loa = decode_names(value)?,
+            "wave" => settings.wave = decode_names(value)?,
+            _ => return Err(format!("unknown setting \"{}\"", key)),
+        }
+    }
+    settings.validate()?;
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Format;
+
+    #[test]
+    fn round_trip() {
+        assert_eq!(encode(&Settings::default()), "v=1");
+        assert_eq!(decode("#v=1").unwrap(), Settings::default());
+
+        let mut settings = Settings::default();
+        settings.options.format = Format::Competition;
+        settings.options.max_level = 195;
+        settings.options.north = 54.9;
+        settings.airspace.atz = AirType::ClassD;
+        settings.airspace.gliding = Some(AirType::Gliding);
+        settings.airspace.home = Some("LASHAM".to_string());
+        settings.rat.insert("ROYAL FLIGHT".to_string());
+        settings.rat.insert("A&B, C".to_string());
+        settings.wave.insert("WEST WALES".to_string());
+
+        let fragment = encode(&settings);
+        assert_eq!(
+            fragment,
+            "v=1&fmt=competition&max=195&n=54.9&atz=classd&gli=gsec&home=LASHAM\
+             &rat=A%26B%2C%20C,ROYAL%20FLIGHT&wave=WEST%20WALES"
+        );
+        assert_eq!(decode(&fragment).unwrap(), settings);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            decode("fmt=openair").unwrap_err(),
+            "missing settings link version"
+        );
+        assert_eq!(
+            decode("v=2&fmt=openair").unwrap_err(),
+            "unsupported settings link version 2"
+        );
+        assert_eq!(
+            decode("v=1&max=high").unwrap_err(),
+            "invalid number \"high\""
+        );
+        assert_eq!(
+            decode("v=1&colour=red").unwrap_err(),
+            "unknown setting \"colour\""
+        );
+        assert_eq!(decode("v=1&rat=%2").unwrap_err(), "invalid encoding \"%2\"");
+        assert_eq!(decode("v=1&atz=x").unwrap_err(), "invalid ATZ type \"x\"");
+    }
+}
@@ -1,113 +1,306 @@
-use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use crate::profiles::{ProfileAction, Profiles};
+use crate::AirspaceSetting;
+use asselect::settings::{AirType, Airspace, Format, Options, Settings};
 use std::rc::Rc;
 use yew::Reducible;
 
-// Airspace types
-#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
-pub enum AirType {
-    ClassA,
-    ClassB,
-    ClassC,
-    ClassD,
-    ClassE,
-    ClassF,
-    ClassG,
-    Danger,
-    Cta,
-    Ctr,
-    Gliding,
-    Matz,
-    Other,
-    Prohibited,
-    Restricted,
-    Rmz,
-    Tmz,
+// Application state
+#[derive(Debug, Default, PartialEq)]
+pub struct State {
+    pub settings: Settings,
+    // Stored profiles, the current one is updated on save
+    pub profiles: Profiles,
+    // Problem loading the stored profiles
+    pub storage_error: Option<String>,
 }
 
-// Output format
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
-pub enum Format {
-    OpenAir,
-    RatOnly,
-    Competition,
+impl State {
+    // Stored profiles, with settings from a shared link in preference to
+    // the current profile
+    pub fn load(link: Option<&Settings>) -> Self {
+        let (profiles, storage_error) = Profiles::load();
+        State::new(profiles, storage_error, link)
+    }
+
+    // Settings from a shared link don't belong to any profile until saved
+    fn new(profiles: Profiles, storage_error: Option<String>, link: Option<&Settings>) -> Self {
+        let settings = match link {
+            Some(settings) => Settings {
+                profile: None,
+                ..settings.clone()
+            },
+            None => profiles.settings(),
+        };
+        State {
+            settings,
+            profiles,
+            storage_error,
+        }
+    }
 }
 
-// Airspace settings
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
-pub struct Airspace {
-    pub atz: AirType,
-    pub ils: Option<AirType>,
-    pub unlicensed: Option<AirType>,
-    pub microlight: Option<AirType>,
-    pub gliding: Option<AirType>,
-    pub home: Option<String>,
-    pub hirta_gvs: Option<AirType>,
-    pub obstacle: bool,
+// Form select control, with option values and labels
+pub struct Control {
+    pub name: &'static str,
+    pub label: &'static str,
+    pub options: &'static [(&'static str, &'static str)],
 }
 
-impl Default for Airspace {
-    fn default() -> Self {
-        Airspace {
-            atz: AirType::Ctr,
-            ils: None,
-            unlicensed: None,
-            microlight: None,
-            gliding: None,
-            home: None,
-            hirta_gvs: None,
-            obstacle: false,
-        }
+impl Control {
+    // Setting for an option value
+    pub fn setting(&self, value: &str) -> Result<Setting, String> {
+        Setting::try_from(AirspaceSetting {
+            name: self.name.to_string(),
+            value: value.to_string(),
+        })
     }
 }
 
-// Additional options
-#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
-pub struct Options {
-    pub max_level: u16,
-    pub radio: bool,
-    pub north: f64,
-    pub south: f64,
-    pub format: Format,
+// Home airfield control, the gliding sites are added to its options
+pub const HOME: &str = "home";
+pub const NO_HOME: &str = "None";
+
+// Airspace tab controls, in pairs on each row
+pub const AIRSPACE_CONTROLS: &[Control] = &[
+    Control {
+        name: "atz",
+        label: "ATZ:",
+        options: &[("classd", "Class D"), ("ctr", "Control Zone")],
+    },
+    Control {
+        name: "ils",
+        label: "ILS Feather:",
+        options: &[
+            ("atz", "As ATZ"),
+            ("classf", "Class F"),
+            ("classg", "Class G"),
+        ],
+    },
+    Control {
+        name: "unlicensed",
+        label: "Unlicensed Airfield:",
+        options: &[
+            ("exclude", "Exclude"),
+            ("classf", "Class F"),
+            ("classg", "Class G"),
+        ],
+    },
+    Control {
+        name: "microlight",
+        label: "Microlight Airfield:",
+        options: &[
+            ("exclude", "Exclude"),
+            ("classf", "Class F"),
+            ("classg", "Class G"),
+        ],
+    },
+    Control {
+        name: "gliding",
+        label: "Gliding Airfield:",
+        options: &[
+            ("exclude", "Exclude"),
+            ("gsec", "Gliding Sector"),
+            ("classf", "Class F"),
+            ("classg", "Class G"),
+        ],
+    },
+    Control {
+        name: HOME,
+        label: "Exclude Home Airfield:",
+        options: &[(NO_HOME, "None")],
+    },
+    Control {
+        name: "radio",
+        label: "Append Frequency to Name:",
+        options: &[("no", "No"), ("yes", "Yes")],
+    },
+];
+
+// Options tab controls, in pairs on each row
+pub const OPTIONS_CONTROLS: &[Control] = &[
+    Control {
+        name: "format",
+        label: "Format:",
+        options: &[
+            ("openair", "OpenAir"),
+            ("ratonly", "RA(T) only"),
+            ("competition", "Competition"),
+            ("geojson", "GeoJSON"),
+            ("tnp", "TNP"),
+            ("kml", "KML (3D)"),
+        ],
+    },
+    Control {
+        name: "max_level",
+        label: "Maximum Level:",
+        options: &[
+            ("600", "Unlimited"),
+            ("195", "FL195"),
+            ("125", "FL125"),
+            ("105", "FL105"),
+            ("65", "FL65"),
+        ],
+    },
+    Control {
+        name: "north",
+        label: "Omit North of:",
+        options: &[
+            ("90.0", "None"),
+            ("54.9", "Carlisle"),
+            ("53.7", "Hull"),
+            ("52.9", "Nottingham"),
+        ],
+    },
+    Control {
+        name: "south",
+        label: "Omit South of:",
+        options: &[
+            ("-90.0", "None"),
+            ("51.8", "Oxford"),
+            ("52.9", "Nottingham"),
+            ("53.7", "Hull"),
+            ("54.9", "Carlisle"),
+        ],
+    },
+    Control {
+        name: "hirta_gvs",
+        label: "HIRTA/GVS:",
+        options: &[
+            ("exclude", "Exclude"),
+            ("danger", "Danger"),
+            ("restricted", "Restricted"),
+        ],
+    },
+    Control {
+        name: "obstacle",
+        label: "Obstacle:",
+        options: &[("exclude", "Exclude"), ("include", "Include")],
+    },
+];
+
+// A single validated setting, one per form control
+#[derive(Clone, Debug, PartialEq)]
+pub enum Setting {
+    Atz(AirType),
+    Ils(Option<AirType>),
+    Unlicensed(Option<AirType>),
+    Microlight(Option<AirType>),
+    Gliding(Option<AirType>),
+    Home(Option<String>),
+    HirtaGvs(Option<AirType>),
+    Obstacle(bool),
+    MaxLevel(u16),
+    Radio(bool),
+    North(f64),
+    South(f64),
+    Format(Format),
 }
 
-impl Default for Options {
-    fn default() -> Self {
-        Options {
-            max_level: 600,
-            radio: false,
-            north: 59.0,
-            south: 49.0,
-            format: Format::OpenAir,
-        }
+impl TryFrom<AirspaceSetting> for Setting {
+    type Error = String;
+
+    // Convert form control name and value
+    fn try_from(setting: AirspaceSetting) -> Result<Self, Self::Error> {
+        let AirspaceSetting { name, value } = setting;
+        let invalid = || format!("invalid {} setting \"{}\"", name, value);
+
+        let setting = match name.as_str() {
+            "atz" => Setting::Atz(match value.as_str() {
+                "classd" => AirType::ClassD,
+                "ctr" => AirType::Ctr,
+                _ => return Err(invalid()),
+            }),
+            "ils" => Setting::Ils(match value.as_str() {
+                "atz" => None,
+                _ => Some(air_type(&value).ok_or_else(invalid)?),
+            }),
+            "unlicensed" => Setting::Unlicensed(optional_air_type(&value).ok_or_else(invalid)?),
+            "microlight" => Setting::Microlight(optional_air_type(&value).ok_or_else(invalid)?),
+            "gliding" => Setting::Gliding(optional_air_type(&value).ok_or_else(invalid)?),
+            "hirta_gvs" => Setting::HirtaGvs(optional_air_type(&value).ok_or_else(invalid)?),
+            HOME => Setting::Home(if value == NO_HOME { None } else { Some(value) }),
+            "obstacle" => Setting::Obstacle(match value.as_str() {
+                "include" => true,
+                "exclude" => false,
+                _ => return Err(invalid()),
+            }),
+            "max_level" => Setting::MaxLevel(value.parse().map_err(|_| invalid())?),
+            "radio" => Setting::Radio(match value.as_str() {
+                "yes" => true,
+                "no" => false,
+                _ => return Err(invalid()),
+            }),
+            "north" => Setting::North(latitude(&value).ok_or_else(invalid)?),
+            "south" => Setting::South(latitude(&value).ok_or_else(invalid)?),
+            "format" => Setting::Format(value.parse()?),
+            _ => return Err(format!("unknown setting \"{}\"", name)),
+        };
+        Ok(setting)
     }
 }
 
-// Application settings
-#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
-pub struct Settings {
-    pub airspace: Airspace,
-    pub options: Options,
-    pub loa: HashSet<String>,
-    pub rat: HashSet<String>,
-    pub wave: HashSet<String>,
-}
+impl Setting {
+    // Check the setting is consistent with the rest of the settings, e.g.
+    // the south limit is south of the north limit
+    pub fn check(&self, settings: &Settings) -> Result<(), String> {
+        let mut settings = settings.clone();
+        self.clone().apply(&mut settings);
+        settings.validate()
+    }
 
-// Application state
-#[derive(Debug, Default, PartialEq)]
-pub struct State {
-    pub settings: Settings,
+    // Setting matches the current settings
+    pub fn selected(&self, airspace: &Airspace, options: &Options) -> bool {
+        match self {
+            Setting::Atz(value) => airspace.atz == *value,
+            Setting::Ils(value) => airspace.ils == *value,
+            Setting::Unlicensed(value) => airspace.unlicensed == *value,
+            Setting::Microlight(value) => airspace.microlight == *value,
+            Setting::Gliding(value) => airspace.gliding == *value,
+            Setting::Home(value) => airspace.home == *value,
+            Setting::HirtaGvs(value) => airspace.hirta_gvs == *value,
+            Setting::Obstacle(value) => airspace.obstacle == *value,
+            Setting::MaxLevel(value) => options.max_level == *value,
+            Setting::Radio(value) => options.radio == *value,
+            Setting::North(value) => (options.north - value).abs() < 0.1,
+            Setting::South(value) => (options.south - value).abs() < 0.1,
+            Setting::Format(value) => options.format == *value,
+        }
+    }
+
+    // Update settings
+    pub fn apply(self, settings: &mut Settings) {
+        let airspace = &mut settings.airspace;
+        let options = &mut settings.options;
+        match self {
+            Setting::Atz(value) => airspace.atz = value,
+            Setting::Ils(value) => airspace.ils = value,
+            Setting::Unlicensed(value) => airspace.unlicensed = value,
+            Setting::Microlight(value) => airspace.microlight = value,
+            Setting::Gliding(value) => airspace.gliding = value,
+            Setting::Home(value) => airspace.home = value,
+            Setting::HirtaGvs(value) => airspace.hirta_gvs = value,
+            Setting::Obstacle(value) => airspace.obstacle = value,
+            Setting::MaxLevel(value) => options.max_level = value,
+            Setting::Radio(value) => options.radio = value,
+            Setting::North(value) => options.north = value,
+            Setting::South(value) => options.south = value,
+            Setting::Format(value) => options.format = value,
+        }
+    }
 }
 
 // State actions
 pub enum Action {
-    Set { name: String, value: String },
+    Set(Setting),
     SetLoa { name: String, checked: bool },
     SetRat { name: String, checked: bool },
     SetWave { name: String, checked: bool },
     ClearLoa,
     ClearRat,
     ClearWave,
+    Profile(ProfileAction),
+    Import(Box<Settings>),
+    Save,
 }
 
 impl Reducible for State {
@@ -115,35 +308,13 @@ impl Reducible for State {
 
     fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
         let mut set = self.settings.clone();
+        let mut profiles = self.profiles.clone();
         match action {
-            // Set airspace option
-            Action::Set { name, value } => {
-                match name.as_str() {
-                    "unlicensed" => set.airspace.unlicensed = default_set(value.as_str()),
-                    "microlight" => set.airspace.microlight = default_set(value.as_str()),
-                    "gliding" => set.airspace.gliding = default_set(value.as_str()),
-                    "hirta_gvs" => set.airspace.hirta_gvs = default_set(value.as_str()),
-                    "obstacle" => set.airspace.obstacle = value == "include",
-                    "max_level" => set.options.max_level = value.parse::<u16>().unwrap(),
-                    "radio" => set.options.radio = value == "yes",
-                    "north" => set.options.north = value.parse::<f64>().unwrap(),
-                    "south" => set.options.south = value.parse::<f64>().unwrap(),
-                    "atz" => {
-                        set.airspace.atz = match value.as_str() {
-                            "classd" => AirType::ClassD,
-                            _ => AirType::Ctr,
-                        }
-                    }
-                    "home" => set.airspace.home = if value == "None" { None } else { Some(value) },
-                    "format" => {
-                        set.options.format = match value.as_str() {
-                            "ratonly" => Format::RatOnly,
-                            "competition" => Format::Competition,
-                            _ => Format::OpenAir,
-                        }
-                    }
-                    _ => (),
-                };
+            // Set airspace option, if consistent with the other settings
+            Action::Set(setting) => {
+                if setting.check(&set).is_ok() {
+                    setting.apply(&mut set);
+                }
             }
             // Include/exclude LOA
             Action::SetLoa { name, checked } => {
@@ -181,13 +352,41 @@ impl Reducible for State {
             Action::ClearWave => {
                 set.wave.clear();
             }
+            // Change profile, keeping the current settings if they belong
+            // to a profile
+            Action::Profile(action) => {
+                if set.profile.is_some() {
+                    profiles.update(&set);
+                }
+                profiles.apply(action);
+                profiles.store();
+                set = profiles.settings();
+            }
+            // Replace settings, keeping the profile
+            Action::Import(settings) => {
+                set = Settings {
+                    profile: set.profile,
+                    ..*settings
+                };
+            }
+            // Save settings in local storage
+            Action::Save => {
+                profiles.save(&set);
+                profiles.store();
+                set = profiles.settings();
+            }
         }
-        Self { settings: set }.into()
+        Self {
+            settings: set,
+            profiles,
+            storage_error: self.storage_error.clone(),
+        }
+        .into()
     }
 }
 
-// Default mapping value to airspace type
-fn default_set(value: &str) -> Option<AirType> {
+// Map form value to airspace type
+fn air_type(value: &str) -> Option<AirType> {
     match value {
         "classf" => Some(AirType::ClassF),
         "classg" => Some(AirType::ClassG),
@@ -197,3 +396,145 @@ fn default_set(value: &str) -> Option<AirType> {
         _ => None,
     }
 }
+
+// Map form value to airspace type, or None if excluded
+fn optional_air_type(value: &str) -> Option<Option<AirType>> {
+    match value {
+        "exclude" => Some(None),
+        _ => air_type(value).map(Some),
+    }
+}
+
+fn latitude(value: &str) -> Option<f64> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|lat| (-90.0..=90.0).contains(lat))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn set(name: &str, value: &str) -> Result<Settings, String> {
+        let mut settings = Settings::default();
+        Setting::try_from(AirspaceSetting {
+            name: name.to_string(),
+            value: value.to_string(),
+        })?
+        .apply(&mut settings);
+        Ok(settings)
+    }
+
+    #[test]
+    fn form_controls() {
+        let controls: Vec<&Control> = AIRSPACE_CONTROLS.iter().chain(OPTIONS_CONTROLS).collect();
+
+        // Every control sets something different
+        let names: HashSet<&str> = controls.iter().map(|c| c.name).collect();
+        assert_eq!(names.len(), controls.len());
+
+        // Every option is valid and gives different settings, with only
+        // that option selected
+        for control in controls {
+            let mut values: Vec<&str> = control.options.iter().map(|(value, _)| *value).collect();
+            if control.name == HOME {
+                values.push("LASHAM");
+            }
+            assert!(values.len() > 1, "{}", control.name);
+
+            let results: Vec<Settings> = values
+                .iter()
+                .map(|value| set(control.name, value).unwrap_or_else(|e| panic!("{}", e)))
+                .collect();
+            for (i, a) in results.iter().enumerate() {
+                for (j, b) in results.iter().enumerate().skip(i + 1) {
+                    assert_ne!(a, b, "{}: {} and {}", control.name, values[i], values[j]);
+                }
+
+                let selected: Vec<&str> = values
+                    .iter()
+                    .copied()
+                    .filter(|value| {
+                        control
+                            .setting(value)
+                            .unwrap()
+                            .selected(&a.airspace, &a.options)
+                    })
+                    .collect();
+                assert_eq!(selected, [values[i]], "{}", control.name);
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_values() {
+        assert_eq!(
+            set("ils", "classx").unwrap_err(),
+            "invalid ils setting \"classx\""
+        );
+        assert_eq!(
+            set("max_level", "FL100").unwrap_err(),
+            "invalid max_level setting \"FL100\""
+        );
+        assert_eq!(
+            set("north", "91").unwrap_err(),
+            "invalid north setting \"91\""
+        );
+        assert_eq!(set("format", "pdf").unwrap_err(), "unknown format \"pdf\"");
+        assert_eq!(
+            set("colour", "red").unwrap_err(),
+            "unknown setting \"colour\""
+        );
+
+        let settings = set("ils", "classg").unwrap();
+        assert_eq!(settings.airspace.ils, Some(AirType::ClassG));
+    }
+
+    #[test]
+    fn link_settings() {
+        let mut profiles = Profiles::default();
+        let mut settings = profiles.settings();
+        settings.options.max_level = 195;
+        profiles.update(&settings);
+
+        let mut link = Settings::default();
+        link.options.max_level = 65;
+        link.rat.insert("ROYAL FLIGHT".to_string());
+        let state = State::new(profiles.clone(), None, Some(&link));
+        assert_eq!(state.settings.profile, None);
+        assert_eq!(state.settings.options.max_level, 65);
+
+        // Changes to the link settings leave the stored profile alone
+        let state = Rc::new(state).reduce(Action::ClearRat);
+        assert_eq!(state.profiles, profiles);
+
+        // Saving adds a profile
+        let mut saved = state.profiles.clone();
+        saved.save(&state.settings);
+        assert_eq!(saved.names(), ["Default", "Shared link"]);
+        assert_eq!(saved.profiles[0].settings.options.max_level, 195);
+        assert_eq!(saved.settings().options.max_level, 65);
+    }
+
+    #[test]
+    fn latitude_limits() {
+        let state = Rc::new(State::default());
+        let state = state.reduce(Action::Set(Setting::North(53.7)));
+        assert_eq!(state.settings.options.north, 53.7);
+
+        // South limit north of the north limit
+        let setting = Setting::South(54.9);
+        assert_eq!(
+            setting.check(&state.settings).unwrap_err(),
+            "south latitude 54.9 isn't south of north latitude 53.7"
+        );
+        let state = state.reduce(Action::Set(setting));
+        assert_eq!(state.settings.options.south, -90.0);
+
+        let state = state.reduce(Action::Set(Setting::South(52.9)));
+        assert_eq!(state.settings.options.south, 52.9);
+        assert!(Setting::North(52.9).check(&state.settings).is_err());
+    }
+}
@@ -0,0 +1,275 @@
+//! Versioned browser storage of settings profiles.
+//!
+//! Stored data records its version, and older data, back to the plain
+//! settings saved before there were profiles (version 0), is upgraded one
+//! version at a time on the raw JSON before deserializing. Profiles that
+//! still can't be loaded are left out, keeping the rest.
+
+use crate::profiles::Profiles;
+use asselect::settings::{Options, Profile, OLD_NORTH_NONE, OLD_SOUTH_NONE};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+// Version of the stored data
+pub const STORAGE_VERSION: u64 = 1;
+
+// Upgrade stored data from one version to the next
+type Migration = fn(&mut Value) -> Result<(), String>;
+
+// Migrations from version 0 up to STORAGE_VERSION
+const MIGRATIONS: &[Migration] = &[settings_to_profiles];
+
+#[derive(Serialize)]
+struct Stored<'a> {
+    version: u64,
+    #[serde(flatten)]
+    profiles: &'a Profiles,
+}
+
+// Version 0 was a single settings object, with 59N and 49N meaning no
+// latitude limits
+fn settings_to_profiles(value: &mut Value) -> Result<(), String> {
+    if !value.is_object() {
+        return Err("settings aren't a JSON object".to_string());
+    }
+
+    let default = Options::default();
+    if let Some(options) = value.get_mut("options").and_then(Value::as_object_mut) {
+        for (name, old, new) in [
+            ("north", OLD_NORTH_NONE, default.north),
+            ("south", OLD_SOUTH_NONE, default.south),
+        ] {
+            let lat = options.get(name).and_then(Value::as_f64);
+            if lat.is_some_and(|lat| (lat - old).abs() < 0.1) {
+                options.insert(name.to_string(), new.into());
+            }
+        }
+    }
+
+    *value = json!({
+        "current": 0,
+        "profiles": [{"name": "Default", "settings": value.take()}]
+    });
+    Ok(())
+}
+
+// Unversioned data is version 0 plain settings
+fn stored_version(value: &Value) -> Result<u64, String> {
+    match value.get("version") {
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| format!("invalid stored settings version {}", version)),
+        None => Ok(0),
+    }
+}
+
+// Apply migrations, the first of which upgrades from version 0
+fn migrate(value: &mut Value, migrations: &[Migration]) -> Result<(), String> {
+    let current = migrations.len() as u64;
+    let version = stored_version(value)?;
+    if version > current {
+        return Err(format!(
+            "stored settings version {} is newer than supported version {}",
+            version, current
+        ));
+    }
+
+    for (from, migration) in (version..).zip(&migrations[version as usize..]) {
+        migration(value)
+            .map_err(|e| format!("can't migrate stored settings version {}: {}", from, e))?;
+        value["version"] = Value::from(from + 1);
+    }
+    Ok(())
+}
+
+// Migrate, deserialize and check stored profiles. Profiles that can't be
+// loaded are left out, with an error for each
+pub fn from_str(data: &str) -> Result<(Profiles, Vec<String>), String> {
+    let mut value: Value = serde_json::from_str(data).map_err(|e| e.to_string())?;
+    migrate(&mut value, MIGRATIONS)?;
+
+    let current = value["current"].as_u64().ok_or("missing current profile")? as usize;
+    let stored = value["profiles"].as_array().ok_or("missing profiles")?;
+
+    let mut profiles = Profiles {
+        current: 0,
+        profiles: Vec::new(),
+    };
+    let mut errors = Vec::new();
+    for (n, stored) in stored.iter().enumerate() {
+        let profile = serde_json::from_value::<Profile>(stored.clone())
+            .map_err(|e| e.to_string())
+            .and_then(|profile| profile.settings.validate().map(|_| profile));
+        match profile {
+            Ok(profile) => {
+                if n == current {
+                    profiles.current = profiles.profiles.len();
+                }
+                profiles.profiles.push(profile);
+            }
+            Err(e) => match stored["name"].as_str() {
+                Some(name) => errors.push(format!("profile \"{}\": {}", name, e)),
+                None => errors.push(format!("profile {}: {}", n + 1, e)),
+            },
+        }
+    }
+
+    if profiles.profiles.is_empty() {
+        return Err(if errors.is_empty() {
+            "no stored profiles".to_string()
+        } else {
+            errors.join("; ")
+        });
+    }
+    Ok((profiles, errors))
+}
+
+pub fn to_string(profiles: &Profiles) -> String {
+    serde_json::to_string(&Stored {
+        version: STORAGE_VERSION,
+        profiles,
+    })
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asselect::settings::Settings;
+
+    fn load(data: &str) -> Profiles {
+        let (profiles, errors) = from_str(data).unwrap();
+        assert!(errors.is_empty(), "{:?}", errors);
+        profiles
+    }
+
+    #[test]
+    fn versions() {
+        assert_eq!(MIGRATIONS.len() as u64, STORAGE_VERSION);
+
+        let mut settings = Settings::default();
+        settings.options.max_level = 195;
+
+        // Version 0, plain settings
+        let v0 = serde_json::to_string(&settings).unwrap();
+        let profiles = load(&v0);
+        assert_eq!(profiles.names(), ["Default"]);
+        assert_eq!(profiles.settings().options.max_level, 195);
+
+        // Current version round trip
+        let mut v1 = Profiles::default();
+        v1.profiles.push(Profile::new("Competition", &settings));
+        v1.current = 1;
+        let data = to_string(&v1);
+        assert!(data.starts_with(r#"{"version":1,"current":1,"#), "{}", data);
+        assert_eq!(load(&data), v1);
+    }
+
+    #[test]
+    fn old_latitude_limits() {
+        let mut value = serde_json::to_value(Settings::default()).unwrap();
+        value["options"]["north"] = OLD_NORTH_NONE.into();
+        value["options"]["south"] = OLD_SOUTH_NONE.into();
+        migrate(&mut value, MIGRATIONS).unwrap();
+
+        let options = &value["profiles"][0]["settings"]["options"];
+        assert_eq!(
+            (options["north"].as_f64(), options["south"].as_f64()),
+            (Some(90.0), Some(-90.0))
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            from_str(r#"{"version": 2, "current": 0, "profiles": []}"#).unwrap_err(),
+            "stored settings version 2 is newer than supported version 1"
+        );
+        assert_eq!(
+            from_str(r#"[1, 2]"#).unwrap_err(),
+            "can't migrate stored settings version 0: settings aren't a JSON object"
+        );
+        assert_eq!(
+            from_str(r#"{"version": 1, "current": 0, "profiles": []}"#).unwrap_err(),
+            "no stored profiles"
+        );
+
+        let mut profiles = Profiles::default();
+        profiles.profiles[0].settings.options.north = 100.0;
+        assert_eq!(
+            from_str(&to_string(&profiles)).unwrap_err(),
+            "profile \"Default\": north latitude 100 is out of range"
+        );
+    }
+
+    #[test]
+    fn bad_profiles() {
+        let mut stored = Profiles::default();
+        for name in ["Competition", "North", "South"] {
+            stored
+                .profiles
+                .push(Profile::new(name, &Settings::default()));
+        }
+        stored.current = 3;
+
+        // A wrong type, and an invalid setting
+        let mut value: Value = serde_json::from_str(&to_string(&stored)).unwrap();
+        value["profiles"][1]["settings"]["options"]["max_level"] = "high".into();
+        value["profiles"][2]["settings"]["options"]["south"] = 91.into();
+
+        let (profiles, errors) = from_str(&value.to_string()).unwrap();
+        assert_eq!(profiles.names(), ["Default", "South"]);
+        assert_eq!(profiles.current, 1);
+        assert_eq!(errors.len(), 2);
+        assert!(
+            errors[0].starts_with("profile \"Competition\": invalid type"),
+            "{}",
+            errors[0]
+        );
+        assert_eq!(
+            errors[1],
+            "profile \"North\": south latitude 91 is out of range"
+        );
+
+        // Current profile left out
+        value["current"] = 1.into();
+        let (profiles, _) = from_str(&value.to_string()).unwrap();
+        assert_eq!(profiles.current, 0);
+    }
+
+    #[test]
+    fn added_fields() {
+        // Airspace and Options fields added since the profiles were stored
+        let mut value: Value = serde_json::from_str(&to_string(&Profiles::default())).unwrap();
+        let settings = &mut value["profiles"][0]["settings"];
+        for (group, field) in [("airspace", "obstacle"), ("options", "radio")] {
+            settings[group].as_object_mut().unwrap().remove(field);
+        }
+        assert_eq!(load(&value.to_string()), Profiles::default());
+    }
+
+    #[test]
+    fn migration_chain() {
+        // Hypothetical version 2 renaming an Options field
+        fn rename_levels(value: &mut Value) -> Result<(), String> {
+            for profile in value["profiles"].as_array_mut().ok_or("no profiles")? {
+                let options = &mut profile["settings"]["options"];
+                let level = options["max_level"].take();
+                options["max_fl"] = level;
+            }
+            Ok(())
+        }
+        let migrations: &[Migration] = &[settings_to_profiles, rename_levels];
+
+        let mut value = serde_json::to_value(Settings::default()).unwrap();
+        migrate(&mut value, migrations).unwrap();
+        assert_eq!(value["version"], 2);
+        assert_eq!(value["profiles"][0]["settings"]["options"]["max_fl"], 600);
+
+        let mut value = json!({"version": 1, "current": 0, "profiles": 1});
+        assert_eq!(
+            migrate(&mut value, migrations).unwrap_err(),
+            "can't migrate stored settings version 1: no profiles"
+        );
+    }
+}
@@ -0,0 +1,355 @@
+//! Competition task, used to select only the airspace near the task legs.
+//!
+//! A task can be loaded from JSON, e.g.
+//!
+//! ```json
+//! {"buffer": "5 nm",
+//!  "turnpoints": [{"name": "LASHAM", "position": "511112N 0010155W", "radius": "3 km"},
+//!                 {"name": "DIDCOT", "position": "513725N 0011906W", "radius": "0.5 km"}]}
+//! ```
+//!
+//! or from the first task in a SeeYou .cup file.
+
+use crate::yaixm::geo::{Distance, DistanceUnit, LatLon};
+use crate::yaixm::shape::{Projection, Shape};
+use crate::yaixm::Volume;
+use serde::{Deserialize, Serialize};
+
+// Default lateral buffer either side of the task
+pub const DEFAULT_BUFFER: Distance = Distance {
+    value: 5.0,
+    unit: DistanceUnit::Nm,
+};
+
+// Longest great circle segment (nm) projected onto a plane when measuring
+// distance to a leg, keeping the projection error well below the buffer
+const MAX_SEGMENT: f64 = 5.0;
+
+fn default_buffer() -> Distance {
+    DEFAULT_BUFFER
+}
+
+fn default_radius() -> Distance {
+    Distance::from_nm(0.0)
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Turnpoint {
+    pub name: String,
+    pub position: LatLon,
+    #[serde(default = "default_radius")]
+    pub radius: Distance,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Task {
+    #[serde(default = "default_buffer")]
+    pub buffer: Distance,
+    pub turnpoints: Vec<Turnpoint>,
+}
+
+// Split CSV line, allowing for quoted fields
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            _ => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields.into_iter().map(|f| f.trim().to_string()).collect()
+}
+
+// SeeYou angle as degrees, decimal minutes and hemisphere, e.g. "5111.200N"
+fn parse_cup_angle(value: &str, deg_len: usize, pos: char, neg: char) -> Option<f64> {
+    if !value.is_ascii() || value.len() < deg_len + 3 {
+        return None;
+    }
+
+    let (digits, hemisphere) = value.split_at(value.len() - 1);
+    let deg = digits[..deg_len].parse::<u32>().ok()? as f64;
+    let min = digits[deg_len..].parse::<f64>().ok()?;
+    if !(0.0..60.0).contains(&min) {
+        return None;
+    }
+
+    match hemisphere {
+        h if h.starts_with(pos) => Some(deg + min / 60.0),
+        h if h.starts_with(neg) => Some(-(deg + min / 60.0)),
+        _ => None,
+    }
+}
+
+// SeeYou distance, e.g. "500m", "3km", "2nm" or "1.5ml"
+fn parse_cup_distance(value: &str) -> Option<Distance> {
+    let split = value.find(|c: char| c.is_ascii_alphabetic())?;
+    let (number, unit) = value.split_at(split);
+    let number = number.parse::<f64>().ok()?;
+    let (value, unit) = match unit {
+        "m" => (number / 1000.0, DistanceUnit::Km),
+        "km" => (number, DistanceUnit::Km),
+        "nm" => (number, DistanceUnit::Nm),
+        "ml" => (number * 1.609344, DistanceUnit::Km),
+        _ => return None,
+    };
+    Some(Distance { value, unit })
+}
+
+// Split leg into short segments along its great circle
+fn segments(a: &LatLon, b: &LatLon) -> Vec<(LatLon, LatLon)> {
+    let length = a.distance(b);
+    let bearing = a.bearing(b);
+    let n = (length / MAX_SEGMENT).ceil().max(1.0) as usize;
+
+    let points: Vec<LatLon> = (0..=n)
+        .map(|i| match i {
+            0 => *a,
+            i if i == n => *b,
+            i => a.destination(bearing, length * i as f64 / n as f64),
+        })
+        .collect();
+    points.windows(2).map(|p| (p[0], p[1])).collect()
+}
+
+impl Task {
+    // Read the first task from a SeeYou .cup file. The task line lists
+    // take-off, start, turnpoints, finish and landing, and observation
+    // zones are numbered from the start
+    pub fn from_cup(text: &str) -> Result<Task, String> {
+        let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+
+        // Waypoints
+        let mut waypoints = Vec::new();
+        for line in lines.by_ref() {
+            if line.starts_with("-----") {
+                break;
+            }
+
+            let fields = split_csv(line);
+            if fields[0].eq_ignore_ascii_case("name") || fields.len() < 5 {
+                continue;
+            }
+
+            let position = parse_cup_angle(&fields[3], 2, 'N', 'S')
+                .zip(parse_cup_angle(&fields[4], 3, 'E', 'W'))
+                .map(|(lat, lon)| LatLon { lat, lon })
+                .ok_or_else(|| format!("invalid waypoint position \"{}\"", line))?;
+            waypoints.push((fields[0].clone(), position));
+        }
+
+        // Task line, ignoring the task name
+        let task = lines.next().ok_or("no task in file")?;
+        let names = split_csv(task);
+        if names.len() < 4 {
+            return Err(format!("task too short \"{}\"", task));
+        }
+
+        let mut turnpoints = names[2..names.len() - 1]
+            .iter()
+            .map(|name| {
+                waypoints
+                    .iter()
+                    .find(|(wp_name, _)| wp_name == name)
+                    .map(|(_, position)| Turnpoint {
+                        name: name.clone(),
+                        position: *position,
+                        radius: default_radius(),
+                    })
+                    .ok_or_else(|| format!("unknown waypoint \"{}\"", name))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Observation zones
+        for line in lines.take_while(|l| !l.starts_with('"')) {
+            let fields = split_csv(line);
+            let Some(n) = fields[0].strip_prefix("ObsZone=") else {
+                continue;
+            };
+            let n = n
+                .parse::<usize>()
+                .map_err(|_| format!("invalid observation zone \"{}\"", line))?;
+
+            for field in &fields[1..] {
+                if let Some(r1) = field.strip_prefix("R1=") {
+                    let tp = turnpoints
+                        .get_mut(n)
+                        .ok_or_else(|| format!("observation zone {} not in task", n))?;
+                    tp.radius = parse_cup_distance(r1)
+                        .ok_or_else(|| format!("invalid radius \"{}\"", r1))?;
+                }
+            }
+        }
+
+        Ok(Task {
+            buffer: DEFAULT_BUFFER,
+            turnpoints,
+        })
+    }
+
+    // Shortest distance (nm) from the volume to the task legs and
+    // observation zones, zero if they overlap. Legs follow great circles,
+    // and are split into short segments each projected about its midpoint
+    pub fn distance(&self, volume: &Volume) -> f64 {
+        let legs = self
+            .turnpoints
+            .windows(2)
+            .flat_map(|leg| segments(&leg[0].position, &leg[1].position))
+            .map(|(a, b)| {
+                let projection = Projection::new(LatLon {
+                    lat: (a.lat + b.lat) / 2.0,
+                    lon: (a.lon + b.lon) / 2.0,
+                });
+                let shape = Shape::new(&volume.boundary, &projection);
+                shape.line_distance(&projection.project(&a), &projection.project(&b))
+            });
+
+        let zones = self.turnpoints.iter().map(|tp| {
+            let projection = Projection::new(tp.position);
+            let shape = Shape::new(&volume.boundary, &projection);
+            let dist = shape.distance(&projection.project(&tp.position)) - tp.radius.nm();
+            dist.max(0.0)
+        });
+
+        legs.chain(zones).fold(f64::INFINITY, f64::min)
+    }
+
+    // Test if the volume is within the buffer distance of the task
+    pub fn near(&self, volume: &Volume) -> bool {
+        self.distance(volume) <= self.buffer.nm()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::yaixm::geo::EARTH_RADIUS_NM;
+
+    const CUP: &str = r#"name,code,country,lat,lon,elev,style,rwdir,rwlen,freq,desc
+"Lasham","LAS",UK,5111.200N,00101.917W,180.0m,5,090,1000.0m,,
+"Didcot","DID",UK,5137.417N,00119.100W,60.0m,1,,,,
+"Membury","MEM",UK,5128.467N,00134.000W,170.0m,1,,,,
+-----Related Tasks-----
+"Day 1","Lasham","Lasham","Didcot","Membury","Lasham","Lasham"
+Options,NoStart=12:00:00,TaskTime=03:00:00
+ObsZone=0,Style=2,R1=5000m,A1=180,Line=1
+ObsZone=1,Style=1,R1=500m,A1=180
+ObsZone=2,Style=1,R1=0.5km,A1=180
+ObsZone=3,Style=3,R1=1nm,A1=180
+"Day 2","Lasham","Lasham","Membury","Lasham","Lasham"
+"#;
+
+    fn volume(boundary: &str) -> Volume {
+        serde_json::from_str(&format!(
+            r#"{{"lower": "SFC", "upper": "FL100", "boundary": {}}}"#,
+            boundary
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn cup() {
+        let task = Task::from_cup(CUP).unwrap();
+        let names: Vec<&str> = task.turnpoints.iter().map(|tp| tp.name.as_str()).collect();
+        assert_eq!(names, ["Lasham", "Didcot", "Membury", "Lasham"]);
+        assert_eq!(task.turnpoints[0].radius.nm(), 5.0 / 1.852);
+        assert_eq!(task.turnpoints[3].radius.nm(), 1.0);
+        assert!((task.turnpoints[1].position.lat - 51.623617).abs() < 1e-6);
+        assert!((task.turnpoints[1].position.lon + 1.318333).abs() < 1e-6);
+        assert_eq!(task.buffer, DEFAULT_BUFFER);
+
+        assert!(Task::from_cup("name,code\n").is_err());
+        assert!(
+            Task::from_cup(&CUP.replace("\"Didcot\",\"Membury\"", "\"Didcot\",\"X\"")).is_err()
+        );
+    }
+
+    #[test]
+    fn json() {
+        let task: Task = serde_json::from_str(
+            r#"{"turnpoints": [{"name": "A", "position": "510000N 0010000W"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(task.buffer, DEFAULT_BUFFER);
+        assert_eq!(task.turnpoints[0].radius.nm(), 0.0);
+
+        let json = serde_json::to_string(&task).unwrap();
+        assert_eq!(serde_json::from_str::<Task>(&json).unwrap(), task);
+    }
+
+    #[test]
+    fn near() {
+        // Single north/south leg, 60 nm long, along 1W
+        let task: Task = serde_json::from_str(
+            r#"{"buffer": "5 nm",
+                "turnpoints": [{"name": "A", "position": "510000N 0010000W", "radius": "2 nm"},
+                               {"name": "B", "position": "520000N 0010000W"}]}"#,
+        )
+        .unwrap();
+
+        // Circles 3 nm and 8 nm east of the leg
+        let lon = |nm: f64| -1.0 + nm / (60.0 * 51.5_f64.to_radians().cos());
+        let circle = |nm: f64, radius: &str| {
+            let centre = LatLon {
+                lat: 51.5,
+                lon: lon(nm),
+            };
+            volume(&format!(
+                r#"[{{"circle": {{"centre": "{}", "radius": "{}"}}}}]"#,
+                centre, radius
+            ))
+        };
+        assert!((task.distance(&circle(8.0, "1 nm")) - 7.0).abs() < 0.01);
+        assert!(task.near(&circle(3.0, "1 nm")));
+        assert!(!task.near(&circle(8.0, "1 nm")));
+        assert!(task.near(&circle(8.0, "4 nm")));
+
+        // Large polygon containing the whole task
+        let poly = volume(
+            r#"[{"line": ["500000N 0020000W", "530000N 0020000W",
+                          "530000N 0000000W", "500000N 0000000W"]}]"#,
+        );
+        assert_eq!(task.distance(&poly), 0.0);
+
+        // Circle south of the start, reached only by its observation zone
+        let start = volume(r#"[{"circle": {"centre": "505400N 0010000W", "radius": "1 nm"}}]"#);
+        assert!((task.distance(&start) - 3.0).abs() < 0.02);
+        assert!(task.near(&start));
+    }
+
+    #[test]
+    fn long_leg() {
+        // 220 nm east/west leg, the great circle bulges 2.3 nm north of 52N
+        let task: Task = serde_json::from_str(
+            r#"{"turnpoints": [{"name": "A", "position": "520000N 0030000W"},
+                               {"name": "B", "position": "520000N 0030000E"}]}"#,
+        )
+        .unwrap();
+        let (a, b) = (task.turnpoints[0].position, task.turnpoints[1].position);
+
+        // Spherical cross-track distance from the great circle
+        let cross_track = |p: &LatLon| {
+            let d13 = a.distance(p) / EARTH_RADIUS_NM;
+            let angle = (a.bearing(p) - a.bearing(&b)).to_radians();
+            (d13.sin() * angle.sin()).asin().abs() * EARTH_RADIUS_NM
+        };
+
+        for position in ["520000N 0000000W", "523000N 0010000E", "522000N 0020000W"] {
+            let centre: LatLon = position.parse().unwrap();
+            let circle = volume(&format!(
+                r#"[{{"circle": {{"centre": "{}", "radius": "0.5 nm"}}}}]"#,
+                centre
+            ));
+            let expected = cross_track(&centre) - 0.5;
+            let dist = task.distance(&circle);
+            assert!(
+                (dist - expected).abs() < 0.02,
+                "{}: {} {}",
+                position,
+                dist,
+                expected
+            );
+        }
+    }
+}
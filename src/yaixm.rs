@@ -1,9 +1,27 @@
+use geo::{Distance, LatLon};
+use level::Level;
+use serde::de::{self, Deserializer};
 use serde::Deserialize;
+use serde_json::Value;
 use std::collections::HashSet;
 
 pub mod convert;
+pub mod diff;
+pub mod geo;
+pub mod geojson;
+pub mod index;
+pub mod kml;
+pub mod level;
+pub mod linearise;
+pub mod query;
+pub mod schema;
+pub mod shape;
+pub mod tnp;
 pub mod util;
 
+#[cfg(test)]
+pub(crate) mod testdata;
+
 #[derive(Clone, Copy, Deserialize, Debug, Eq, PartialEq)]
 pub enum IcaoClass {
     A,
@@ -86,36 +104,45 @@ pub enum Rule {
     Tmz,
 }
 
-#[derive(Clone, Deserialize, Debug)]
+#[derive(Clone, Deserialize, Debug, PartialEq)]
 pub struct Circle {
-    pub centre: String,
-    pub radius: String,
+    pub centre: LatLon,
+    pub radius: Distance,
 }
 
-#[derive(Clone, Deserialize, Debug)]
+#[derive(Clone, Copy, Deserialize, Debug, Eq, PartialEq)]
+pub enum Direction {
+    #[serde(rename = "cw")]
+    Cw,
+    #[serde(rename = "ccw")]
+    Ccw,
+}
+
+#[derive(Clone, Deserialize, Debug, PartialEq)]
 pub struct Arc {
-    pub centre: String,
-    pub dir: String,
-    pub radius: String,
-    pub to: String,
+    pub centre: LatLon,
+    pub dir: Direction,
+    pub radius: Distance,
+    pub to: LatLon,
 }
 
-#[derive(Clone, Deserialize, Debug)]
+#[derive(Clone, Deserialize, Debug, PartialEq)]
 pub enum Boundary {
     #[serde(rename = "circle")]
     Circle(Circle),
     #[serde(rename = "arc")]
     Arc(Arc),
     #[serde(rename = "line")]
-    Line(Vec<String>),
+    Line(Vec<LatLon>),
 }
 
 #[derive(Clone, Deserialize, Debug)]
+#[serde(remote = "Self")]
 pub struct Volume {
     pub id: Option<String>,
     pub name: Option<String>,
-    pub lower: String,
-    pub upper: String,
+    pub lower: Level,
+    pub upper: Level,
     #[serde(rename = "class")]
     pub icao_class: Option<IcaoClass>,
     pub rules: Option<HashSet<Rule>>,
@@ -126,6 +153,7 @@ pub struct Volume {
 }
 
 #[derive(Clone, Deserialize, Debug)]
+#[serde(remote = "Self")]
 pub struct Feature {
     pub id: Option<String>,
     pub name: String,
@@ -140,6 +168,7 @@ pub struct Feature {
 }
 
 #[derive(Clone, Deserialize, Debug)]
+#[serde(remote = "Self")]
 pub struct Replace {
     pub id: String,
     pub geometry: Vec<Volume>,
@@ -160,10 +189,11 @@ pub struct Loa {
 }
 
 #[derive(Deserialize, Debug)]
+#[serde(remote = "Self")]
 pub struct Obstacle {
-    pub elevation: String,
+    pub elevation: Level,
     pub name: String,
-    pub position: String,
+    pub position: LatLon,
 }
 
 #[derive(Deserialize, Debug)]
@@ -191,3 +221,161 @@ pub struct Yaixm {
     pub service: Vec<Service>,
     pub release: Release,
 }
+
+// Deserialize via an intermediate JSON value so errors can name the
+// feature (or other item) containing the bad data
+fn deserialize_named<'de, D, T>(
+    deserializer: D,
+    kind: &str,
+    key: &str,
+    deserialize: fn(Value) -> Result<T, serde_json::Error>,
+) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Value::deserialize(deserializer)?;
+    let name = value[key].as_str().unwrap_or_default().to_string();
+
+    deserialize(value).map_err(|e| de::Error::custom(format!("{} \"{}\": {}", kind, name, e)))
+}
+
+// A boundary is either a single circle, or starts with a line (so that arcs
+// always have a start point) and has no empty lines
+fn validate_boundary(boundary: &[Boundary]) -> Result<(), &'static str> {
+    match boundary {
+        [Boundary::Circle(_)] => Ok(()),
+        [Boundary::Line(_), rest @ ..] => {
+            if boundary
+                .iter()
+                .any(|x| matches!(x, Boundary::Line(line) if line.is_empty()))
+            {
+                Err("empty line in boundary")
+            } else if rest.iter().any(|x| matches!(x, Boundary::Circle(_))) {
+                Err("circle must be the only boundary segment")
+            } else {
+                Ok(())
+            }
+        }
+        _ => Err("boundary must be a circle or start with a line"),
+    }
+}
+
+impl<'de> Deserialize<'de> for Volume {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let volume = Volume::deserialize(deserializer)?;
+        validate_boundary(&volume.boundary).map_err(de::Error::custom)?;
+        Ok(volume)
+    }
+}
+
+impl<'de> Deserialize<'de> for Feature {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_named(deserializer, "feature", "name", Feature::deserialize)
+    }
+}
+
+impl<'de> Deserialize<'de> for Replace {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_named(deserializer, "replacement", "id", Replace::deserialize)
+    }
+}
+
+impl<'de> Deserialize<'de> for Obstacle {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_named(deserializer, "obstacle", "name", Obstacle::deserialize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feature(boundary: &str) -> Result<Feature, serde_json::Error> {
+        serde_json::from_str(&format!(
+            r#"{{"name": "BAD ONE", "type": "D", "geometry": [
+                 {{"lower": "SFC", "upper": "FL100", "boundary": {}}}
+               ]}}"#,
+            boundary
+        ))
+    }
+
+    #[test]
+    fn boundary_ok() {
+        let f = feature(
+            r#"[{"line": ["513000N 0011500W", "523000N 0011500W"]},
+                {"arc": {"centre": "520000N 0011500W", "dir": "cw",
+                         "radius": "30 nm", "to": "513000N 0011500W"}}]"#,
+        )
+        .unwrap();
+
+        match &f.geometry[0].boundary[1] {
+            Boundary::Arc(arc) => {
+                assert_eq!(
+                    arc.centre,
+                    LatLon {
+                        lat: 52.0,
+                        lon: -1.25
+                    }
+                );
+                assert_eq!(arc.radius.nm(), 30.0);
+                assert_eq!(arc.dir, Direction::Cw);
+            }
+            _ => panic!("expected arc"),
+        }
+    }
+
+    #[test]
+    fn boundary_errors() {
+        let err = feature(r#"[{"line": ["513000N 0011500W", "523000X 0011500W"]}]"#)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("feature \"BAD ONE\""), "{}", err);
+        assert!(
+            err.contains("invalid lat/lon \"523000X 0011500W\""),
+            "{}",
+            err
+        );
+
+        let err = feature(r#"[{"circle": {"centre": "513000N 0011500W", "radius": "5"}}]"#)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("feature \"BAD ONE\""), "{}", err);
+        assert!(err.contains("invalid distance \"5\""), "{}", err);
+
+        let err = feature(
+            r#"[{"line": ["513000N 0011500W", "523000N 0011500W"]},
+                {"arc": {"centre": "520000N 0011500W", "dir": "cc",
+                         "radius": "30 nm", "to": "513000N 0011500W"}}]"#,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("feature \"BAD ONE\""), "{}", err);
+        assert!(err.contains("unknown variant `cc`"), "{}", err);
+
+        for boundary in [
+            r#"[]"#,
+            r#"[{"line": []}]"#,
+            r#"[{"arc": {"centre": "520000N 0011500W", "dir": "cw",
+                         "radius": "30 nm", "to": "513000N 0011500W"}}]"#,
+            r#"[{"line": ["513000N 0011500W"]},
+                {"circle": {"centre": "513000N 0011500W", "radius": "5 nm"}}]"#,
+        ] {
+            let err = feature(boundary).unwrap_err().to_string();
+            assert!(err.starts_with("feature \"BAD ONE\""), "{}", err);
+        }
+    }
+
+    #[test]
+    fn obstacle_error() {
+        let err = serde_json::from_str::<Obstacle>(
+            r#"{"name": "MAST", "elevation": "1200 ft", "position": "513000N"}"#,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(
+            err.starts_with("obstacle \"MAST\": invalid lat/lon"),
+            "{}",
+            err
+        );
+    }
+}
@@ -1,13 +1,20 @@
-use crate::state::{AirType, Format, Settings};
+use crate::airac::{self, Validity};
+use crate::settings::{AirType, Format, Settings};
+use crate::yaixm::geo::{sweep, Distance, DistanceUnit, LatLon};
+use crate::yaixm::geojson::geojson;
+use crate::yaixm::kml::kml;
+use crate::yaixm::level::Level;
+use crate::yaixm::linearise::DEFAULT_RESOLUTION;
+use crate::yaixm::tnp::tnp;
 use crate::yaixm::{
-    Arc, Boundary, Circle, Feature, IcaoClass, IcaoType, Loa, LocalType, Obstacle, Rule, Service,
-    Volume, Yaixm,
+    Arc, Boundary, Circle, Direction, Feature, IcaoClass, IcaoType, Loa, LocalType, Obstacle,
+    Release, Rule, Service, Volume, Yaixm,
 };
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use std::collections::{HashMap, HashSet};
 
 impl IcaoClass {
-    fn as_str(&self) -> &'static str {
+    pub(crate) fn as_str(&self) -> &'static str {
         match self {
             IcaoClass::A => "A",
             IcaoClass::B => "B",
@@ -79,58 +86,82 @@ impl AirType {
     }
 }
 
-// Normalise all levels to flight level
-fn norm_level(value: &str) -> u16 {
-    if let Some(fl) = value.strip_prefix("FL") {
-        fl.parse().unwrap()
-    } else if value.ends_with(" ft") {
-        value.split(' ').next().unwrap().parse::<u16>().unwrap() / 100
-    } else {
-        0
-    }
-}
-
 // Openair level format
-fn format_level(level: &str) -> String {
-    if let Some(alt) = level.strip_suffix(" ft") {
-        // Altitude
-        alt.to_string() + "ALT"
-    } else {
-        // Flight level or SFC
-        level.to_string()
+pub(crate) fn format_level(level: &Level) -> String {
+    match level {
+        Level::Surface => "SFC".to_string(),
+        Level::FlightLevel(fl) => format!("FL{}", fl),
+        Level::Altitude(alt) => format!("{}ALT", alt),
+        Level::Height(hgt) => format!("{}AGL", hgt),
     }
 }
 
 // Openair lat/lon format
-fn format_latlon(latlon: &str) -> String {
+fn format_latlon(latlon: &LatLon) -> String {
+    let (lat, lon) = (latlon.lat_dms(), latlon.lon_dms());
     format!(
-        "{}:{}:{} {} {}:{}:{} {}",
-        &latlon[..2],
-        &latlon[2..4],
-        &latlon[4..6],
-        &latlon[6..7],
-        &latlon[8..11],
-        &latlon[11..13],
-        &latlon[13..15],
-        &latlon[15..16]
+        "{:02}:{:02}:{:02} {} {:03}:{:02}:{:02} {}",
+        lat.deg, lat.min, lat.sec, lat.hemisphere, lon.deg, lon.min, lon.sec, lon.hemisphere
     )
 }
 
 // Openair distance format
-fn format_distance(distance: &str) -> String {
-    match distance.split_once(' ') {
-        Some((dist, unit)) => {
-            if unit == "km" {
-                format!("{:.3}", dist.parse::<f32>().unwrap() / 1.852)
-            } else {
-                dist.to_string()
+fn format_distance(distance: &Distance) -> String {
+    match distance.unit {
+        DistanceUnit::Km => format!("{:.3}", distance.nm()),
+        DistanceUnit::Nm => distance.value.to_string(),
+    }
+}
+
+// Minimum and maximum latitude of a boundary
+fn lat_extent(boundary: &[Boundary]) -> (f64, f64) {
+    let mut min_lat = f64::MAX;
+    let mut max_lat = f64::MIN;
+    let mut add = |lat: f64| {
+        min_lat = min_lat.min(lat);
+        max_lat = max_lat.max(lat);
+    };
+
+    let mut prev = None;
+    for segment in boundary {
+        match segment {
+            Boundary::Line(line) => {
+                for point in line {
+                    add(point.lat);
+                }
+                prev = line.last();
+            }
+            Boundary::Arc(arc) => {
+                let radius = arc.radius.nm() / 60.0;
+                let clockwise = arc.dir == Direction::Cw;
+                let from = arc.centre.bearing(prev.unwrap());
+                let to = arc.centre.bearing(&arc.to);
+
+                // Include the northern/southern extremes if the arc passes
+                // through them
+                add(arc.to.lat);
+                let angle = sweep(from, to, clockwise);
+                if sweep(from, 0.0, clockwise) <= angle {
+                    add(arc.centre.lat + radius);
+                }
+                if sweep(from, 180.0, clockwise) <= angle {
+                    add(arc.centre.lat - radius);
+                }
+                prev = Some(&arc.to);
+            }
+            Boundary::Circle(circle) => {
+                let radius = circle.radius.nm() / 60.0;
+                add(circle.centre.lat - radius);
+                add(circle.centre.lat + radius);
             }
         }
-        _ => "".to_string(),
     }
+
+    (min_lat, max_lat)
 }
+
 // Remove unwanted feature/volume
-fn airfilter(feature: &Feature, vol: &Volume, settings: &Settings) -> bool {
+pub(crate) fn airfilter(feature: &Feature, vol: &Volume, settings: &Settings) -> bool {
     let exclude = match feature.local_type {
         // No-ATZ
         Some(LocalType::NoAtz) => settings.airspace.unlicensed.is_none(),
@@ -152,12 +183,27 @@ fn airfilter(feature: &Feature, vol: &Volume, settings: &Settings) -> bool {
         _ => false,
     };
 
-    !(exclude || (norm_level(&vol.lower) >= settings.options.max_level))
+    // Volumes entirely north or south of the limits
+    let (min_lat, max_lat) = lat_extent(&vol.boundary);
+    let outside = min_lat > settings.options.north || max_lat < settings.options.south;
+
+    // Volumes starting at or above the maximum level
+    let high = vol.lower.feet() >= Level::FlightLevel(settings.options.max_level).feet();
+
+    // Volumes away from the competition task
+    let remote = settings.task.as_ref().is_some_and(|task| !task.near(vol));
+
+    !(exclude || outside || high || remote)
 }
 
 // Give each volume a name
-fn do_name(feature: &Feature, vol: &Volume, n: usize, settings: &Settings) -> String {
-    let name = if let Some(name) = &vol.name {
+pub(crate) fn volume_name(
+    feature: &Feature,
+    vol: &Volume,
+    n: usize,
+    settings: &Settings,
+) -> String {
+    if let Some(name) = &vol.name {
         name.clone()
     } else {
         let mut name = feature.name.clone();
@@ -224,13 +270,11 @@ fn do_name(feature: &Feature, vol: &Volume, n: usize, settings: &Settings) -> St
         };
 
         name
-    };
-
-    format!("AN {}\n", name)
+    }
 }
 
-// Give each volume a type
-fn do_type(feature: &Feature, volume: &Volume, settings: &Settings) -> String {
+// Give each volume a (OpenAir) type
+pub(crate) fn volume_type(feature: &Feature, volume: &Volume, settings: &Settings) -> &'static str {
     let rules = feature
         .rules
         .iter()
@@ -240,7 +284,7 @@ fn do_type(feature: &Feature, volume: &Volume, settings: &Settings) -> String {
 
     let comp = settings.options.format == Format::Competition;
 
-    let openair_type = if rules.contains(&Rule::Notam) {
+    if rules.contains(&Rule::Notam) {
         // NOTAM activated airspace
         "G"
     } else {
@@ -321,9 +365,30 @@ fn do_type(feature: &Feature, volume: &Volume, settings: &Settings) -> String {
                 }
             }
         }
-    };
+    }
+}
 
-    format!("AC {}\n", openair_type)
+// Combined feature and volume rules, sorted by name
+pub(crate) fn volume_rules(feature: &Feature, volume: &Volume) -> Vec<&'static str> {
+    let mut rules = feature
+        .rules
+        .iter()
+        .chain(volume.rules.iter())
+        .flatten()
+        .collect::<HashSet<&Rule>>()
+        .into_iter()
+        .map(|x| x.as_str())
+        .collect::<Vec<&str>>();
+    rules.sort();
+    rules
+}
+
+fn do_name(feature: &Feature, vol: &Volume, n: usize, settings: &Settings) -> String {
+    format!("AN {}\n", volume_name(feature, vol, n, settings))
+}
+
+fn do_type(feature: &Feature, volume: &Volume, settings: &Settings) -> String {
+    format!("AC {}\n", volume_type(feature, volume, settings))
 }
 
 fn do_levels(volume: &Volume) -> String {
@@ -338,15 +403,12 @@ fn do_freq(freq: f64) -> String {
     format!("AF {:.3}\n", freq)
 }
 
-fn do_point(point: &str) -> String {
+fn do_point(point: &LatLon) -> String {
     format!("DP {}\n", format_latlon(point))
 }
 
-fn do_line(line: &[String]) -> String {
-    line.iter()
-        .map(|x| do_point(x))
-        .collect::<Vec<String>>()
-        .join("")
+fn do_line(line: &[LatLon]) -> String {
+    line.iter().map(do_point).collect::<Vec<String>>().join("")
 }
 
 fn do_circle(circle: &Circle) -> String {
@@ -357,8 +419,8 @@ fn do_circle(circle: &Circle) -> String {
     )
 }
 
-fn do_arc(arc: &Arc, from: &str) -> String {
-    let dir = if arc.dir == "cw" { "+" } else { "-" };
+fn do_arc(arc: &Arc, from: &LatLon) -> String {
+    let dir = if arc.dir == Direction::Cw { "+" } else { "-" };
 
     format!(
         "V D={}\nV X={}\nDB {}, {}\n",
@@ -371,17 +433,17 @@ fn do_arc(arc: &Arc, from: &str) -> String {
 
 fn do_boundary(boundary: &[Boundary]) -> String {
     let mut out = String::new();
-    let mut prev = "";
+    let mut prev = None;
 
     for segment in boundary {
         match segment {
             Boundary::Line(line) => {
                 out.push_str(&do_line(line));
-                prev = line.last().unwrap();
+                prev = line.last();
             }
             Boundary::Arc(arc) => {
-                out.push_str(&do_arc(arc, prev));
-                prev = &arc.to;
+                out.push_str(&do_arc(arc, prev.unwrap()));
+                prev = Some(&arc.to);
             }
             Boundary::Circle(circle) => out.push_str(&do_circle(circle)),
         }
@@ -389,7 +451,7 @@ fn do_boundary(boundary: &[Boundary]) -> String {
 
     // Close the polygon
     if let Boundary::Line(line) = &boundary[0] {
-        if line[0] != prev {
+        if Some(&line[0]) != prev {
             out.push_str(&do_point(&line[0]));
         }
     }
@@ -398,7 +460,7 @@ fn do_boundary(boundary: &[Boundary]) -> String {
 }
 
 // Merge radio frequency data
-fn merge_services(airspace: &mut Vec<Feature>, services: &Vec<Service>) {
+pub(crate) fn merge_services(airspace: &mut Vec<Feature>, services: &Vec<Service>) {
     // Create frequency map
     let mut frequencies = HashMap::new();
     for service in services {
@@ -498,11 +560,11 @@ fn add_obstacles(airspace: &mut Vec<Feature>, obstacles: &Vec<Obstacle>) {
             local_type: None,
             rules: None,
             geometry: vec![Volume {
-                upper: obstacle.elevation.clone(),
-                lower: "SFC".to_string(),
+                upper: obstacle.elevation,
+                lower: Level::Surface,
                 boundary: vec![Boundary::Circle(Circle {
-                    centre: obstacle.position.clone(),
-                    radius: "0.5 nm".to_string(),
+                    centre: obstacle.position,
+                    radius: Distance::from_nm(0.5),
                 })],
                 icao_class: None,
                 frequency: None,
@@ -518,8 +580,15 @@ fn add_obstacles(airspace: &mut Vec<Feature>, obstacles: &Vec<Obstacle>) {
 }
 
 // File header
-fn header(note: &str, airac: &str, commit: &str, settings: &Settings) -> String {
-    let mut hdr = "UK Airspace\n\
+pub(crate) fn header(
+    title: &str,
+    release: &Release,
+    produced: DateTime<Utc>,
+    settings: &Settings,
+    comment: &str,
+) -> String {
+    let mut hdr = title.to_string()
+        + "\n\
         Alan Sparrow (airspace@asselect.uk)\n\
         \n\
         I have tried to make this data as accurate as possible but\n\
@@ -531,22 +600,35 @@ fn header(note: &str, airac: &str, commit: &str, settings: &Settings) -> String
         in this file is based on the work of others including: George Knight,\n\
         Geoff Brown, Peter Desmond and Rory O'Connor.  The data is originally\n\
         sourced from the UK Aeronautical Information Package (AIP).\n\
-        \n"
-    .to_string();
-
-    hdr.push_str(note);
-    hdr.push_str(&format!("\nAIRAC: {}\n", &airac[..10]));
-    hdr.push_str(&format!("Commit: {}\n", commit));
-    hdr.push_str(&format!("Produced: {}\n", Utc::now().to_rfc3339()));
+        \n";
+
+    hdr.push_str(&release.note);
+    hdr.push_str(&format!("\nAIRAC: {}\n", &release.airac_date[..10]));
+    if let Ok(status) = airac::status(&release.airac_date, produced.date_naive()) {
+        if status.validity == Validity::Expired {
+            hdr.push_str(&format!(
+                "WARNING: AIRAC cycle {} has expired, cycle {} started {}\n",
+                status.data.ident(),
+                status.current.ident(),
+                status.current.start()
+            ));
+        }
+        hdr.push_str(&format!("Next AIRAC: {}\n", status.current.next().start()));
+    }
+    hdr.push_str(&format!("Commit: {}\n", release.commit));
+    hdr.push_str(&format!("Produced: {}\n", produced.to_rfc3339()));
+    if let Some(profile) = &settings.profile {
+        hdr.push_str(&format!("Profile: {}\n", profile));
+    }
     hdr.push_str(&format!("{:?}", settings));
 
-    // Prepend "*" to lines
+    // Prepend comment marker to lines
     hdr.split('\n')
         .map(|x| {
             if x.is_empty() {
-                "*".to_string()
+                comment.to_string()
             } else {
-                "* ".to_owned() + x
+                format!("{} {}", comment, x)
             }
         })
         .collect::<Vec<String>>()
@@ -554,8 +636,8 @@ fn header(note: &str, airac: &str, commit: &str, settings: &Settings) -> String
         + "\n"
 }
 
-// Generate OpenAir data
-pub fn openair(yaixm: &Yaixm, settings: &Settings) -> String {
+// Main airspace with LOAs and obstacles
+fn base_airspace(yaixm: &Yaixm, settings: &Settings) -> Vec<Feature> {
     let mut airspace = yaixm.airspace.clone();
 
     // Merge LOAs
@@ -571,6 +653,20 @@ pub fn openair(yaixm: &Yaixm, settings: &Settings) -> String {
         add_obstacles(&mut airspace, &yaixm.obstacle);
     }
 
+    airspace
+}
+
+// Airspace features selected by the settings, with LOAs, obstacles, RA(T)s
+// and radio frequencies merged in. Volumes still need to be checked with
+// `airfilter`
+pub(crate) fn merged_airspace(yaixm: &Yaixm, settings: &Settings) -> Vec<Feature> {
+    // RA(T) only output omits the main airspace
+    let mut airspace = if settings.options.format == Format::RatOnly {
+        Vec::new()
+    } else {
+        base_airspace(yaixm, settings)
+    };
+
     // Append RA(T)s
     airspace.append(
         &mut yaixm
@@ -584,10 +680,20 @@ pub fn openair(yaixm: &Yaixm, settings: &Settings) -> String {
     // Merge radio frequencies
     merge_services(&mut airspace, &yaixm.service);
 
+    airspace
+}
+
+// Generate OpenAir data
+pub fn openair(yaixm: &Yaixm, settings: &Settings, produced: DateTime<Utc>) -> String {
+    let title = if settings.options.format == Format::RatOnly {
+        "UK Airspace - Temporary Restrictions, RA(T) only"
+    } else {
+        "UK Airspace"
+    };
+
     // Build OpenAir data
-    let rel = &yaixm.release;
-    let mut output = header(&rel.note, &rel.airac_date, &rel.commit, settings);
-    for feature in airspace {
+    let mut output = header(title, &yaixm.release, produced, settings, "*");
+    for feature in merged_airspace(yaixm, settings) {
         for (n, volume) in feature.geometry.iter().enumerate() {
             if airfilter(&feature, volume, settings) {
                 output.push_str("*\n");
@@ -603,3 +709,177 @@ pub fn openair(yaixm: &Yaixm, settings: &Settings) -> String {
     }
     output
 }
+
+// Generate airspace data in the selected format
+pub fn convert(yaixm: &Yaixm, settings: &Settings, produced: DateTime<Utc>) -> String {
+    match settings.options.format {
+        Format::GeoJson => geojson(yaixm, settings, DEFAULT_RESOLUTION),
+        Format::Tnp => tnp(yaixm, settings, produced),
+        Format::Kml => kml(yaixm, settings, produced, DEFAULT_RESOLUTION),
+        _ => openair(yaixm, settings, produced),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Profile;
+    use crate::yaixm::testdata::yaixm;
+
+    fn feature(boundary: &str) -> Feature {
+        serde_json::from_str(&format!(
+            r#"{{"name": "TEST", "type": "D", "geometry": [
+                 {{"lower": "SFC", "upper": "FL100", "boundary": {}}}
+               ]}}"#,
+            boundary
+        ))
+        .unwrap()
+    }
+
+    fn included(feature: &Feature, north: f64, south: f64) -> bool {
+        let mut settings = Settings::default();
+        settings.options.north = north;
+        settings.options.south = south;
+        airfilter(feature, &feature.geometry[0], &settings)
+    }
+
+    #[test]
+    fn lat_line() {
+        let f =
+            feature(r#"[{"line": ["543000N 0020000W", "550000N 0020000W", "543000N 0010000W"]}]"#);
+        assert!(included(&f, 90.0, -90.0));
+        assert!(included(&f, 54.9, -90.0));
+        assert!(included(&f, 90.0, 54.9));
+        assert!(!included(&f, 54.4, -90.0));
+        assert!(!included(&f, 90.0, 55.1));
+    }
+
+    #[test]
+    fn lat_circle() {
+        // Centre 54.8N, radius 10 nm extends to 54.967N
+        let f = feature(r#"[{"circle": {"centre": "544800N 0020000W", "radius": "10 nm"}}]"#);
+        assert!(included(&f, 90.0, 54.9));
+        assert!(included(&f, 54.7, -90.0));
+        assert!(!included(&f, 90.0, 55.0));
+        assert!(!included(&f, 54.6, -90.0));
+
+        // 5 nm radius doesn't reach 54.9N
+        let f = feature(r#"[{"circle": {"centre": "544800N 0020000W", "radius": "5 nm"}}]"#);
+        assert!(!included(&f, 90.0, 54.9));
+
+        // 15 km radius (8.1 nm) reaches 54.935N
+        let f = feature(r#"[{"circle": {"centre": "544800N 0020000W", "radius": "15 km"}}]"#);
+        assert!(included(&f, 90.0, 54.9));
+        assert!(!included(&f, 90.0, 54.95));
+    }
+
+    #[test]
+    fn lat_arc() {
+        // Semicircle of radius 10 nm bulging north of its 54.8N base line
+        let f = feature(
+            r#"[{"line": ["544800N 0020000W", "544800N 0014239W"]},
+                {"arc": {"centre": "544800N 0020000W", "dir": "ccw",
+                         "radius": "10 nm", "to": "544800N 0021721W"}}]"#,
+        );
+        assert!(included(&f, 90.0, 54.9));
+        assert!(!included(&f, 90.0, 55.0));
+        assert!(!included(&f, 54.7, -90.0));
+
+        // Same semicircle bulging south
+        let f = feature(
+            r#"[{"line": ["544800N 0020000W", "544800N 0014239W"]},
+                {"arc": {"centre": "544800N 0020000W", "dir": "cw",
+                         "radius": "10 nm", "to": "544800N 0021721W"}}]"#,
+        );
+        assert!(!included(&f, 90.0, 54.9));
+        assert!(included(&f, 54.7, -90.0));
+        assert!(!included(&f, 54.6, -90.0));
+    }
+
+    #[test]
+    fn level_filter() {
+        let mut settings = Settings::default();
+        settings.options.max_level = 65;
+
+        for (lower, included) in [
+            ("SFC", true),
+            ("1500 ft agl", true),
+            ("6000 ft", true),
+            ("6500 ft", false),
+            ("FL55", true),
+            ("FL65", false),
+        ] {
+            let f: Feature = serde_json::from_str(&format!(
+                r#"{{"name": "TEST", "type": "D", "geometry": [
+                     {{"lower": "{}", "upper": "FL195", "boundary": [
+                       {{"circle": {{"centre": "520000N 0010000W", "radius": "2 nm"}}}}]}}]}}"#,
+                lower
+            ))
+            .unwrap();
+            assert_eq!(
+                airfilter(&f, &f.geometry[0], &settings),
+                included,
+                "{}",
+                lower
+            );
+        }
+    }
+
+    #[test]
+    fn rat_only() {
+        let mut settings = Settings::default();
+        settings.options.format = Format::RatOnly;
+        settings.options.max_level = 65;
+        settings.options.radio = true;
+        settings.airspace.obstacle = true;
+        settings.rat.insert("ROYAL FLIGHT".to_string());
+
+        let oa = openair(&yaixm(), &settings, Utc::now());
+        assert!(oa.starts_with("* UK Airspace - Temporary Restrictions, RA(T) only\n"));
+        assert!(oa.contains("AN ROYAL FLIGHT 123.450\n"));
+        assert_eq!(oa.matches("\nAN ").count(), 1);
+        assert!(!oa.contains("BRIZE NORTON"));
+        assert!(!oa.contains("AIR SHOW"));
+        assert!(!oa.contains("MAST"));
+    }
+
+    #[test]
+    fn rat_appended() {
+        let mut settings = Settings::default();
+        settings.rat.insert("ROYAL FLIGHT".to_string());
+
+        let oa = openair(&yaixm(), &settings, Utc::now());
+        assert!(oa.starts_with("* UK Airspace\n"));
+        assert!(oa.contains("AN BRIZE NORTON\n"));
+        assert_eq!(oa.matches("AN ROYAL FLIGHT\n").count(), 2);
+        assert!(!oa.contains("AIR SHOW"));
+    }
+
+    #[test]
+    fn airac_header() {
+        let settings = Settings::default();
+        let produced = |date: &str| {
+            DateTime::parse_from_rfc3339(date)
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+
+        let oa = openair(&yaixm(), &settings, produced("2022-09-20T12:00:00Z"));
+        assert!(oa.contains("* AIRAC: 2022-09-08\n* Next AIRAC: 2022-10-06\n"));
+        assert!(!oa.contains("WARNING"));
+        assert!(!oa.contains("* Profile:"));
+
+        let oa = openair(&yaixm(), &settings, produced("2022-10-06T12:00:00Z"));
+        assert!(oa.contains(
+            "* WARNING: AIRAC cycle 2209 has expired, cycle 2210 started 2022-10-06\n\
+             * Next AIRAC: 2022-11-03\n"
+        ));
+    }
+
+    #[test]
+    fn profile_header() {
+        let settings = Profile::new("Club", &Settings::default()).settings();
+        let oa = openair(&yaixm(), &settings, Utc::now());
+        assert!(oa.contains("\n* Profile: Club\n"));
+    }
+}
@@ -0,0 +1,456 @@
+//! Compare two YAIXM releases.
+//!
+//! Airspace and RA(T) features are matched by id, falling back to name,
+//! and their volumes by id, then name. Volumes with neither are matched to
+//! an identical volume, then by position in the feature.
+
+use crate::settings::Settings;
+use crate::yaixm::convert::{merge_services, volume_name, volume_rules};
+use crate::yaixm::{Boundary, Direction, Feature, Volume, Yaixm};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct VolumeRecord {
+    pub name: String,
+    pub id: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Change {
+    pub field: String,
+    pub old: String,
+    pub new: String,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Modified {
+    pub volume: VolumeRecord,
+    pub changes: Vec<Change>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Diff {
+    pub old_release: String,
+    pub new_release: String,
+    pub added: Vec<VolumeRecord>,
+    pub removed: Vec<VolumeRecord>,
+    pub modified: Vec<Modified>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "Changes from {} to {}\n",
+            self.old_release, self.new_release
+        );
+        if self.is_empty() {
+            text.push_str("No airspace changes\n");
+        }
+
+        let mut section = |title: &str, prefix: &str, records: &[&VolumeRecord]| {
+            if !records.is_empty() {
+                writeln!(text, "\n{}:", title).unwrap();
+            }
+            for record in records {
+                writeln!(text, "{} {}", prefix, record).unwrap();
+            }
+        };
+        section("Added", "+", &self.added.iter().collect::<Vec<_>>());
+        section("Removed", "-", &self.removed.iter().collect::<Vec<_>>());
+
+        if !self.modified.is_empty() {
+            text.push_str("\nModified:\n");
+        }
+        for modified in &self.modified {
+            writeln!(text, "* {}", modified.volume).unwrap();
+            for change in &modified.changes {
+                writeln!(
+                    text,
+                    "    {}: {} -> {}",
+                    change.field, change.old, change.new
+                )
+                .unwrap();
+            }
+        }
+        text
+    }
+}
+
+impl std::fmt::Display for VolumeRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.id {
+            Some(id) => write!(f, "{} [{}]", self.name, id),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+// Release description, e.g. "AIRAC 2022-09-08 (abc123)"
+fn release_name(yaixm: &Yaixm) -> String {
+    let release = &yaixm.release;
+    format!(
+        "AIRAC {} ({})",
+        release.airac_date.get(..10).unwrap_or(&release.airac_date),
+        release.commit
+    )
+}
+
+fn boundary_text(boundary: &[Boundary]) -> String {
+    boundary
+        .iter()
+        .map(|segment| match segment {
+            Boundary::Line(points) => {
+                let points: Vec<String> = points.iter().map(|p| p.to_string()).collect();
+                format!("line {}", points.join(", "))
+            }
+            Boundary::Arc(arc) => format!(
+                "arc {} {} {} to {}",
+                match arc.dir {
+                    Direction::Cw => "cw",
+                    Direction::Ccw => "ccw",
+                },
+                arc.radius,
+                arc.centre,
+                arc.to
+            ),
+            Boundary::Circle(circle) => format!("circle {} {}", circle.radius, circle.centre),
+        })
+        .collect::<Vec<String>>()
+        .join("; ")
+}
+
+fn class_text(feature: &Feature, volume: &Volume) -> String {
+    volume
+        .icao_class
+        .or(feature.icao_class)
+        .map_or("-", |c| c.as_str())
+        .to_string()
+}
+
+fn rules_text(feature: &Feature, volume: &Volume) -> String {
+    let rules = volume_rules(feature, volume);
+    if rules.is_empty() {
+        "-".to_string()
+    } else {
+        rules.join(",")
+    }
+}
+
+fn frequency_text(volume: &Volume) -> String {
+    volume
+        .frequency
+        .map_or("-".to_string(), |f| format!("{:.3}", f))
+}
+
+fn record(feature: &Feature, n: usize) -> VolumeRecord {
+    let volume = &feature.geometry[n];
+    VolumeRecord {
+        name: volume_name(feature, volume, n, &Settings::default()),
+        id: volume.id.clone().or_else(|| feature.id.clone()),
+    }
+}
+
+fn feature_key(feature: &Feature) -> (Option<&String>, Option<&String>) {
+    (feature.id.as_ref(), Some(&feature.name))
+}
+
+fn unidentified(volume: &Volume) -> bool {
+    volume.id.is_none() && volume.name.is_none()
+}
+
+fn volume_key(volume: &Volume) -> (Option<&String>, Option<&String>) {
+    (volume.id.as_ref(), volume.name.as_ref())
+}
+
+// Index of the matching item, by id then by name, ignoring matched items
+fn find_match<T>(
+    items: &[T],
+    matched: &[bool],
+    id: Option<&String>,
+    name: Option<&String>,
+    key: impl Fn(&T) -> (Option<&String>, Option<&String>),
+) -> Option<usize> {
+    let unmatched = |i: &usize| !matched[*i];
+    id.and_then(|id| {
+        (0..items.len())
+            .filter(unmatched)
+            .find(|&i| key(&items[i]).0 == Some(id))
+    })
+    .or_else(|| {
+        name.and_then(|name| {
+            (0..items.len())
+                .filter(unmatched)
+                .find(|&i| key(&items[i]).1 == Some(name))
+        })
+    })
+}
+
+fn compare_volumes(
+    old_feature: &Feature,
+    old: &Volume,
+    new_feature: &Feature,
+    new: &Volume,
+) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let mut check = |field: &str, old: String, new: String| {
+        if old != new {
+            changes.push(Change {
+                field: field.to_string(),
+                old,
+                new,
+            });
+        }
+    };
+
+    check("lower", old.lower.to_string(), new.lower.to_string());
+    check("upper", old.upper.to_string(), new.upper.to_string());
+    check(
+        "class",
+        class_text(old_feature, old),
+        class_text(new_feature, new),
+    );
+    check(
+        "rules",
+        rules_text(old_feature, old),
+        rules_text(new_feature, new),
+    );
+    check("frequency", frequency_text(old), frequency_text(new));
+    if old.boundary != new.boundary {
+        check(
+            "boundary",
+            boundary_text(&old.boundary),
+            boundary_text(&new.boundary),
+        );
+    }
+    changes
+}
+
+fn compare_features(old: &[Feature], new: &[Feature], diff: &mut Diff) {
+    let mut old_matched = vec![false; old.len()];
+    for new_feature in new {
+        let Some(i) = find_match(
+            old,
+            &old_matched,
+            new_feature.id.as_ref(),
+            Some(&new_feature.name),
+            feature_key,
+        ) else {
+            diff.added
+                .extend((0..new_feature.geometry.len()).map(|n| record(new_feature, n)));
+            continue;
+        };
+        old_matched[i] = true;
+        let old_feature = &old[i];
+
+        // Match volumes by id or name. Volumes with neither are matched to
+        // an identical volume, then by position, so inserting a volume
+        // doesn't shift the rest
+        let old_vols = &old_feature.geometry;
+        let mut vol_matched = vec![false; old_vols.len()];
+        let mut matches = vec![None; new_feature.geometry.len()];
+        for (n, new_vol) in new_feature.geometry.iter().enumerate() {
+            let m = find_match(
+                old_vols,
+                &vol_matched,
+                new_vol.id.as_ref(),
+                new_vol.name.as_ref(),
+                volume_key,
+            )
+            .or_else(|| {
+                unidentified(new_vol)
+                    .then(|| {
+                        (0..old_vols.len()).find(|&m| {
+                            !vol_matched[m]
+                                && unidentified(&old_vols[m])
+                                && compare_volumes(old_feature, &old_vols[m], new_feature, new_vol)
+                                    .is_empty()
+                        })
+                    })
+                    .flatten()
+            });
+            if let Some(m) = m {
+                vol_matched[m] = true;
+                matches[n] = Some(m);
+            }
+        }
+        for (n, new_vol) in new_feature.geometry.iter().enumerate() {
+            // Only unidentified volumes can be matched by position
+            if matches[n].is_none()
+                && unidentified(new_vol)
+                && n < old_vols.len()
+                && !vol_matched[n]
+                && unidentified(&old_vols[n])
+            {
+                vol_matched[n] = true;
+                matches[n] = Some(n);
+            }
+        }
+
+        for (n, new_vol) in new_feature.geometry.iter().enumerate() {
+            match matches[n] {
+                Some(m) => {
+                    let changes = compare_volumes(old_feature, &old_vols[m], new_feature, new_vol);
+                    if !changes.is_empty() {
+                        diff.modified.push(Modified {
+                            volume: record(new_feature, n),
+                            changes,
+                        });
+                    }
+                }
+                None => diff.added.push(record(new_feature, n)),
+            }
+        }
+
+        diff.removed.extend(
+            (0..old_feature.geometry.len())
+                .filter(|&m| !vol_matched[m])
+                .map(|m| record(old_feature, m)),
+        );
+    }
+
+    for (i, feature) in old.iter().enumerate() {
+        if !old_matched[i] {
+            diff.removed
+                .extend((0..feature.geometry.len()).map(|n| record(feature, n)));
+        }
+    }
+}
+
+// Features with frequencies from the release's services
+fn with_frequencies(features: &[Feature], yaixm: &Yaixm) -> Vec<Feature> {
+    let mut features = features.to_vec();
+    merge_services(&mut features, &yaixm.service);
+    features
+}
+
+// Changes to airspace and RA(T) volumes between two releases
+pub fn diff(old: &Yaixm, new: &Yaixm) -> Diff {
+    let mut diff = Diff {
+        old_release: release_name(old),
+        new_release: release_name(new),
+        ..Default::default()
+    };
+    for (old_features, new_features) in [(&old.airspace, &new.airspace), (&old.rat, &new.rat)] {
+        compare_features(
+            &with_frequencies(old_features, old),
+            &with_frequencies(new_features, new),
+            &mut diff,
+        );
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::yaixm::testdata;
+    use serde_json::Value;
+
+    fn modified_yaixm(modify: impl Fn(&mut Value)) -> Yaixm {
+        let mut value: Value = serde_json::from_str(testdata::YAIXM).unwrap();
+        modify(&mut value);
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn unchanged() {
+        let d = diff(&testdata::yaixm(), &testdata::yaixm());
+        assert!(d.is_empty());
+        assert_eq!(
+            d.to_text(),
+            "Changes from AIRAC 2022-09-08 (abc) to AIRAC 2022-09-08 (abc)\n\
+             No airspace changes\n"
+        );
+    }
+
+    #[test]
+    fn changes() {
+        let new = modified_yaixm(|v| {
+            v["release"]["commit"] = "def".into();
+
+            // Renamed, but same id
+            v["airspace"][0]["name"] = "BRIZE".into();
+            let vol = &mut v["airspace"][0]["geometry"][0];
+            vol["upper"] = "4500 ft".into();
+            vol["class"] = "C".into();
+            vol["rules"] = serde_json::json!(["TMZ"]);
+            vol["boundary"][0]["circle"]["radius"] = "6 nm".into();
+
+            // New Brize Norton frequency
+            v["service"][1]["frequency"] = 119.005.into();
+
+            // Second RAT volume removed, and a new RAT
+            v["rat"][0]["geometry"].as_array_mut().unwrap().pop();
+            v["rat"][1]["name"] = "AIR DISPLAY".into();
+        });
+
+        let d = diff(&testdata::yaixm(), &new);
+        assert_eq!(
+            d.added,
+            [VolumeRecord {
+                name: "AIR DISPLAY".to_string(),
+                id: None
+            }]
+        );
+        let removed: Vec<String> = d.removed.iter().map(|r| r.to_string()).collect();
+        assert_eq!(removed, ["ROYAL FLIGHT [royal]", "AIR SHOW"]);
+
+        assert_eq!(d.modified.len(), 1);
+        let fields: Vec<&str> = d.modified[0]
+            .changes
+            .iter()
+            .map(|c| c.field.as_str())
+            .collect();
+        assert_eq!(fields, ["upper", "class", "rules", "frequency", "boundary"]);
+
+        let text = d.to_text();
+        assert!(text.starts_with("Changes from AIRAC 2022-09-08 (abc) to AIRAC 2022-09-08 (def)\n"));
+        assert!(text.contains(
+            "* BRIZE [brize]\n    upper: 3500 ft -> 4500 ft\n    class: D -> C\n    rules: - -> TMZ\n    \
+             frequency: 119.000 -> 119.005\n"
+        ));
+        assert!(text.contains(
+            "    boundary: circle 5 nm 514500N 0013500W -> circle 6 nm 514500N 0013500W\n"
+        ));
+
+        let json: Diff = serde_json::from_str(&d.to_json()).unwrap();
+        assert_eq!(json, d);
+    }
+
+    #[test]
+    fn inserted_volume() {
+        let volume = serde_json::json!({
+            "lower": "SFC", "upper": "FL45", "boundary": [
+              {"circle": {"centre": "521000N 0010000W", "radius": "2 nm"}}]});
+
+        // Named volume inserted before the unnamed ones
+        let mut named = volume.clone();
+        named["name"] = "ROYAL FLIGHT EAST".into();
+        let new = modified_yaixm(|v| {
+            let geometry = v["rat"][0]["geometry"].as_array_mut().unwrap();
+            geometry.insert(0, named.clone());
+        });
+        let d = diff(&testdata::yaixm(), &new);
+        let added: Vec<String> = d.added.iter().map(|r| r.to_string()).collect();
+        assert_eq!(added, ["ROYAL FLIGHT EAST [royal]"]);
+        assert!(d.removed.is_empty() && d.modified.is_empty());
+
+        // Unnamed volume inserted
+        let new = modified_yaixm(|v| {
+            let geometry = v["rat"][0]["geometry"].as_array_mut().unwrap();
+            geometry.insert(0, volume.clone());
+        });
+        let d = diff(&testdata::yaixm(), &new);
+        assert_eq!(d.added.len(), 1);
+        assert!(d.removed.is_empty() && d.modified.is_empty());
+    }
+}
@@ -0,0 +1,312 @@
+use serde::de::{self, Deserialize, Deserializer};
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+// Mean earth radius
+pub const EARTH_RADIUS_NM: f64 = 3440.065;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LatLon {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+// Angle as degrees, minutes and (rounded) seconds
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Dms {
+    pub deg: u32,
+    pub min: u32,
+    pub sec: u32,
+    pub hemisphere: char,
+}
+
+impl Dms {
+    fn new(value: f64, pos: char, neg: char) -> Dms {
+        let secs = (value.abs() * 3600.0).round() as u32;
+        Dms {
+            deg: secs / 3600,
+            min: (secs / 60) % 60,
+            sec: secs % 60,
+            hemisphere: if value < 0.0 { neg } else { pos },
+        }
+    }
+}
+
+// Parse degrees, minutes, (optionally decimal) seconds and hemisphere,
+// e.g. "0012345W" or "512345.5N"
+fn parse_dms(value: &str, deg_len: usize, pos: char, neg: char, max: f64) -> Option<f64> {
+    if !value.is_ascii() || value.len() < deg_len + 5 {
+        return None;
+    }
+
+    let (digits, hemisphere) = value.split_at(value.len() - 1);
+    let (whole, frac) = digits.split_once('.').unwrap_or((digits, "0"));
+    if whole.len() != deg_len + 4
+        || !whole
+            .bytes()
+            .chain(frac.bytes())
+            .all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let deg = whole[..deg_len].parse::<f64>().ok()?;
+    let min = whole[deg_len..deg_len + 2].parse::<f64>().ok()?;
+    let sec = digits[deg_len + 2..].parse::<f64>().ok()?;
+    let angle = deg + min / 60.0 + sec / 3600.0;
+    if min >= 60.0 || sec >= 60.0 || angle > max {
+        return None;
+    }
+
+    match hemisphere.chars().next() {
+        Some(h) if h == pos => Some(angle),
+        Some(h) if h == neg => Some(-angle),
+        _ => None,
+    }
+}
+
+impl LatLon {
+    pub fn lat_dms(&self) -> Dms {
+        Dms::new(self.lat, 'N', 'S')
+    }
+
+    pub fn lon_dms(&self) -> Dms {
+        Dms::new(self.lon, 'E', 'W')
+    }
+
+    // Great circle distance in nautical miles
+    pub fn distance(&self, other: &LatLon) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (other.lon - self.lon).to_radians();
+
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * a.sqrt().asin() * EARTH_RADIUS_NM
+    }
+
+    // Initial great circle bearing (degrees, 0 to 360) to another point
+    pub fn bearing(&self, other: &LatLon) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let dlon = (other.lon - self.lon).to_radians();
+
+        let y = dlon.sin() * lat2.cos();
+        let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
+        y.atan2(x).to_degrees().rem_euclid(360.0)
+    }
+
+    // Point at given bearing (degrees) and distance (nautical miles)
+    pub fn destination(&self, bearing: f64, distance: f64) -> LatLon {
+        let lat1 = self.lat.to_radians();
+        let brg = bearing.to_radians();
+        let d = distance / EARTH_RADIUS_NM;
+
+        let lat2 = (lat1.sin() * d.cos() + lat1.cos() * d.sin() * brg.cos()).asin();
+        let dlon = (brg.sin() * d.sin() * lat1.cos()).atan2(d.cos() - lat1.sin() * lat2.sin());
+
+        LatLon {
+            lat: lat2.to_degrees(),
+            lon: self.lon + dlon.to_degrees(),
+        }
+    }
+}
+
+// YAIXM lat/lon, e.g. "512345N 0012345W"
+impl FromStr for LatLon {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let err = || format!("invalid lat/lon \"{}\"", value);
+
+        let (lat, lon) = value.split_once(' ').ok_or_else(err)?;
+        Ok(LatLon {
+            lat: parse_dms(lat, 2, 'N', 'S', 90.0).ok_or_else(err)?,
+            lon: parse_dms(lon, 3, 'E', 'W', 180.0).ok_or_else(err)?,
+        })
+    }
+}
+
+impl fmt::Display for LatLon {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (lat, lon) = (self.lat_dms(), self.lon_dms());
+        write!(
+            f,
+            "{:02}{:02}{:02}{} {:03}{:02}{:02}{}",
+            lat.deg, lat.min, lat.sec, lat.hemisphere, lon.deg, lon.min, lon.sec, lon.hemisphere
+        )
+    }
+}
+
+impl Serialize for LatLon {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for LatLon {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DistanceUnit {
+    Nm,
+    Km,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Distance {
+    pub value: f64,
+    pub unit: DistanceUnit,
+}
+
+impl Distance {
+    pub fn from_nm(value: f64) -> Distance {
+        Distance {
+            value,
+            unit: DistanceUnit::Nm,
+        }
+    }
+
+    // Distance in nautical miles
+    pub fn nm(&self) -> f64 {
+        match self.unit {
+            DistanceUnit::Nm => self.value,
+            DistanceUnit::Km => self.value / 1.852,
+        }
+    }
+}
+
+// YAIXM distance, e.g. "5 nm" or "2.5 km"
+impl FromStr for Distance {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let err = || format!("invalid distance \"{}\"", value);
+
+        let (dist, unit) = value.split_once(' ').ok_or_else(err)?;
+        let unit = match unit {
+            "nm" => DistanceUnit::Nm,
+            "km" => DistanceUnit::Km,
+            _ => return Err(err()),
+        };
+        match dist.parse::<f64>() {
+            Ok(value) if value.is_finite() && value >= 0.0 => Ok(Distance { value, unit }),
+            _ => Err(err()),
+        }
+    }
+}
+
+impl fmt::Display for Distance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unit = match self.unit {
+            DistanceUnit::Nm => "nm",
+            DistanceUnit::Km => "km",
+        };
+        write!(f, "{} {}", self.value, unit)
+    }
+}
+
+impl Serialize for Distance {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Distance {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+// Angle (degrees) swept from bearing `from` to bearing `to`
+pub fn sweep(from: f64, to: f64, clockwise: bool) -> f64 {
+    if clockwise {
+        (to - from).rem_euclid(360.0)
+    } else {
+        (from - to).rem_euclid(360.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64, tol: f64) -> bool {
+        (a - b).abs() < tol
+    }
+
+    #[test]
+    fn parse_latlon() {
+        let p = "513000N 0011500W".parse::<LatLon>().unwrap();
+        assert_eq!(
+            p,
+            LatLon {
+                lat: 51.5,
+                lon: -1.25
+            }
+        );
+        assert_eq!(p.to_string(), "513000N 0011500W");
+
+        let p = "100036S 1000000E".parse::<LatLon>().unwrap();
+        assert!(close(p.lat, -10.01, 1e-9));
+        assert_eq!(p.lon, 100.0);
+
+        let p = "513029.5N 0011500W".parse::<LatLon>().unwrap();
+        assert!(close(p.lat, 51.0 + 30.0 / 60.0 + 29.5 / 3600.0, 1e-9));
+        assert_eq!(p.to_string(), "513030N 0011500W");
+
+        for bad in [
+            "",
+            "513000N",
+            "513000N0011500W",
+            "513000X 0011500W",
+            "516000N 0011500W",
+            "913000N 0011500W",
+            "51300N 0011500W",
+            "513000N 011500W",
+            "5130+0N 0011500W",
+            "513000N 0011500N",
+            "513000N 0011500É",
+        ] {
+            assert!(bad.parse::<LatLon>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn parse_distance() {
+        let d = "5 nm".parse::<Distance>().unwrap();
+        assert_eq!(d.nm(), 5.0);
+        assert_eq!(d.to_string(), "5 nm");
+
+        let d = "1.852 km".parse::<Distance>().unwrap();
+        assert_eq!(d.unit, DistanceUnit::Km);
+        assert_eq!(d.nm(), 1.0);
+
+        for bad in ["", "5", "5nm", "5 miles", "x nm", "-1 nm", "NaN nm"] {
+            assert!(bad.parse::<Distance>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn destination() {
+        let p = LatLon {
+            lat: 52.0,
+            lon: -1.0,
+        };
+
+        // One minute of latitude is (about) one nautical mile
+        let q = p.destination(0.0, 60.0);
+        assert!(close(q.lat, 53.0, 0.01));
+        assert!(close(q.lon, -1.0, 1e-9));
+
+        let q = p.destination(123.0, 20.0);
+        assert!(close(p.distance(&q), 20.0, 1e-6));
+        assert!(close(p.bearing(&q), 123.0, 1e-6));
+    }
+}
@@ -0,0 +1,120 @@
+use crate::settings::Settings;
+use crate::yaixm::convert::{airfilter, merged_airspace, volume_name, volume_rules, volume_type};
+use crate::yaixm::geo::LatLon;
+use crate::yaixm::linearise::{self, Resolution};
+use crate::yaixm::{Feature, Volume, Yaixm};
+use serde_json::{json, Value};
+
+// Polygon ring in GeoJSON [lon, lat] order, anti-clockwise as
+// recommended by RFC 7946
+fn ring(points: &[LatLon]) -> Vec<[f64; 2]> {
+    let mut ring = points.iter().map(|p| [p.lon, p.lat]).collect::<Vec<_>>();
+
+    let area: f64 = ring
+        .windows(2)
+        .map(|w| w[0][0] * w[1][1] - w[1][0] * w[0][1])
+        .sum();
+    if area < 0.0 {
+        ring.reverse();
+    }
+    ring
+}
+
+fn do_feature(
+    feature: &Feature,
+    volume: &Volume,
+    n: usize,
+    settings: &Settings,
+    resolution: Resolution,
+) -> Value {
+    let rules = volume_rules(feature, volume);
+
+    json!({
+        "type": "Feature",
+        "geometry": {
+            "type": "Polygon",
+            "coordinates": [ring(&linearise::volume(volume, resolution))],
+        },
+        "properties": {
+            "name": volume_name(feature, volume, n, settings),
+            "type": volume_type(feature, volume, settings),
+            "lower": volume.lower.to_string(),
+            "upper": volume.upper.to_string(),
+            "frequency": volume.frequency,
+            "rules": rules,
+            "feature_id": feature.id,
+            "volume_id": volume.id,
+        },
+    })
+}
+
+// Generate GeoJSON FeatureCollection, arcs and circles are converted to
+// points at the given resolution
+pub fn geojson(yaixm: &Yaixm, settings: &Settings, resolution: Resolution) -> String {
+    let mut features = Vec::new();
+    for feature in merged_airspace(yaixm, settings) {
+        for (n, volume) in feature.geometry.iter().enumerate() {
+            if airfilter(&feature, volume, settings) {
+                features.push(do_feature(&feature, volume, n, settings, resolution));
+            }
+        }
+    }
+
+    let mut collection = json!({
+        "type": "FeatureCollection",
+        "features": features,
+    });
+    if let Some(profile) = &settings.profile {
+        collection["profile"] = json!(profile);
+    }
+    collection.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Format;
+    use crate::yaixm::testdata::yaixm;
+
+    #[test]
+    fn feature_collection() {
+        let mut settings = Settings::default();
+        settings.options.format = Format::GeoJson;
+        settings.rat.insert("ROYAL FLIGHT".to_string());
+
+        let value: Value =
+            serde_json::from_str(&geojson(&yaixm(), &settings, Resolution::Step(10.0))).unwrap();
+        assert_eq!(value["type"], "FeatureCollection");
+
+        let features = value["features"].as_array().unwrap();
+        assert_eq!(features.len(), 3);
+
+        let brize = &features[0];
+        assert_eq!(brize["geometry"]["type"], "Polygon");
+        assert_eq!(
+            brize["geometry"]["coordinates"][0]
+                .as_array()
+                .unwrap()
+                .len(),
+            37
+        );
+        assert_eq!(
+            brize["properties"],
+            json!({
+                "name": "BRIZE NORTON",
+                "type": "D",
+                "lower": "SFC",
+                "upper": "3500 ft",
+                "frequency": 119.0,
+                "rules": [],
+                "feature_id": "brize",
+                "volume_id": null,
+            })
+        );
+
+        let royal = &features[2];
+        assert_eq!(royal["properties"]["type"], "P");
+        assert_eq!(royal["properties"]["lower"], "FL70");
+        assert_eq!(royal["properties"]["frequency"], 123.45);
+    }
+}
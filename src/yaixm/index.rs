@@ -0,0 +1,379 @@
+//! Spatial index of volume bounding boxes.
+//!
+//! The index is a static R-tree, bulk loaded with the sort-tile-recursive
+//! method, built once for a list of features, e.g. `Yaixm::airspace` or
+//! the merged airspace. Queries return candidate volumes whose bounding
+//! boxes match, and exact tests are left to the caller.
+
+use crate::yaixm::geo::LatLon;
+use crate::yaixm::linearise::{self, Resolution};
+use crate::yaixm::{Feature, Volume};
+
+// Maximum entries in each tree node
+const NODE_SIZE: usize = 16;
+
+// Arcs and circles are linearised and the box padded by the chord error
+const CHORD_ERROR: f64 = 0.01;
+
+// Latitude/longitude bounding box, degrees
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BBox {
+    pub min_lat: f64,
+    pub min_lon: f64,
+    pub max_lat: f64,
+    pub max_lon: f64,
+}
+
+impl BBox {
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a LatLon>) -> BBox {
+        let empty = BBox {
+            min_lat: f64::INFINITY,
+            min_lon: f64::INFINITY,
+            max_lat: f64::NEG_INFINITY,
+            max_lon: f64::NEG_INFINITY,
+        };
+        points.into_iter().fold(empty, |bbox, p| BBox {
+            min_lat: bbox.min_lat.min(p.lat),
+            min_lon: bbox.min_lon.min(p.lon),
+            max_lat: bbox.max_lat.max(p.lat),
+            max_lon: bbox.max_lon.max(p.lon),
+        })
+    }
+
+    // Bounding box of the volume's boundary
+    pub fn from_volume(volume: &Volume) -> BBox {
+        let bbox = BBox::from_points(&linearise::volume(
+            volume,
+            Resolution::ChordError(CHORD_ERROR),
+        ));
+
+        // Pad by the chord error
+        let dlat = CHORD_ERROR / 60.0;
+        let dlon = dlat
+            / bbox
+                .max_lat
+                .abs()
+                .max(bbox.min_lat.abs())
+                .to_radians()
+                .cos();
+        BBox {
+            min_lat: bbox.min_lat - dlat,
+            min_lon: bbox.min_lon - dlon,
+            max_lat: bbox.max_lat + dlat,
+            max_lon: bbox.max_lon + dlon,
+        }
+    }
+
+    fn union(&self, other: &BBox) -> BBox {
+        BBox {
+            min_lat: self.min_lat.min(other.min_lat),
+            min_lon: self.min_lon.min(other.min_lon),
+            max_lat: self.max_lat.max(other.max_lat),
+            max_lon: self.max_lon.max(other.max_lon),
+        }
+    }
+
+    fn centre(&self) -> LatLon {
+        LatLon {
+            lat: (self.min_lat + self.max_lat) / 2.0,
+            lon: (self.min_lon + self.max_lon) / 2.0,
+        }
+    }
+
+    pub fn contains(&self, p: &LatLon) -> bool {
+        (self.min_lat..=self.max_lat).contains(&p.lat)
+            && (self.min_lon..=self.max_lon).contains(&p.lon)
+    }
+
+    pub fn intersects(&self, other: &BBox) -> bool {
+        self.min_lat <= other.max_lat
+            && other.min_lat <= self.max_lat
+            && self.min_lon <= other.max_lon
+            && other.min_lon <= self.max_lon
+    }
+
+    // Test if the (straight, in lat/lon) line from a to b crosses the box
+    pub fn intersects_line(&self, a: &LatLon, b: &LatLon) -> bool {
+        // Clip the line parameter against each pair of sides
+        let mut t0: f64 = 0.0;
+        let mut t1: f64 = 1.0;
+        for (start, delta, min, max) in [
+            (a.lat, b.lat - a.lat, self.min_lat, self.max_lat),
+            (a.lon, b.lon - a.lon, self.min_lon, self.max_lon),
+        ] {
+            if delta == 0.0 {
+                if start < min || start > max {
+                    return false;
+                }
+            } else {
+                let (ta, tb) = ((min - start) / delta, (max - start) / delta);
+                t0 = t0.max(ta.min(tb));
+                t1 = t1.min(ta.max(tb));
+                if t0 > t1 {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+// Reference to a volume, indices into the features and their geometry
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct VolumeRef {
+    pub feature: usize,
+    pub volume: usize,
+}
+
+#[derive(Debug)]
+enum Children {
+    // Indices of entries
+    Leaf(Vec<usize>),
+    // Indices of nodes
+    Inner(Vec<usize>),
+}
+
+#[derive(Debug)]
+struct Node {
+    bbox: BBox,
+    children: Children,
+}
+
+#[derive(Debug)]
+pub struct SpatialIndex {
+    entries: Vec<(BBox, VolumeRef)>,
+    nodes: Vec<Node>,
+    root: Option<usize>,
+}
+
+// Sort-tile-recursive grouping of boxes into nodes of (at most) NODE_SIZE
+fn tile(mut items: Vec<(BBox, usize)>) -> Vec<Vec<(BBox, usize)>> {
+    let n_nodes = items.len().div_ceil(NODE_SIZE);
+    let n_slices = (n_nodes as f64).sqrt().ceil() as usize;
+    let slice_size = n_slices * NODE_SIZE;
+
+    items.sort_by(|a, b| a.0.centre().lon.total_cmp(&b.0.centre().lon));
+
+    let mut groups = Vec::new();
+    for slice in items.chunks_mut(slice_size.max(1)) {
+        slice.sort_by(|a, b| a.0.centre().lat.total_cmp(&b.0.centre().lat));
+        groups.extend(slice.chunks(NODE_SIZE).map(|c| c.to_vec()));
+    }
+    groups
+}
+
+impl SpatialIndex {
+    // Index all volumes of the features
+    pub fn new(airspace: &[Feature]) -> SpatialIndex {
+        SpatialIndex::with_filter(airspace, |_, _| true)
+    }
+
+    // Index the volumes selected by the filter
+    pub fn with_filter<F>(airspace: &[Feature], filter: F) -> SpatialIndex
+    where
+        F: Fn(&Feature, &Volume) -> bool,
+    {
+        let entries: Vec<(BBox, VolumeRef)> = airspace
+            .iter()
+            .enumerate()
+            .flat_map(|(f, feature)| {
+                feature
+                    .geometry
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, volume)| filter(feature, volume))
+                    .map(move |(v, volume)| {
+                        let vref = VolumeRef {
+                            feature: f,
+                            volume: v,
+                        };
+                        (BBox::from_volume(volume), vref)
+                    })
+            })
+            .collect();
+
+        let mut nodes = Vec::new();
+
+        // Leaf nodes
+        let items = entries.iter().map(|e| e.0).enumerate().map(|(i, b)| (b, i));
+        let mut level: Vec<(BBox, usize)> = tile(items.collect())
+            .into_iter()
+            .map(|group| {
+                let node = Node {
+                    bbox: group.iter().skip(1).fold(group[0].0, |b, g| b.union(&g.0)),
+                    children: Children::Leaf(group.iter().map(|g| g.1).collect()),
+                };
+                nodes.push(node);
+                (nodes[nodes.len() - 1].bbox, nodes.len() - 1)
+            })
+            .collect();
+
+        // Inner nodes, up to the root
+        while level.len() > 1 {
+            level = tile(level)
+                .into_iter()
+                .map(|group| {
+                    let node = Node {
+                        bbox: group.iter().skip(1).fold(group[0].0, |b, g| b.union(&g.0)),
+                        children: Children::Inner(group.iter().map(|g| g.1).collect()),
+                    };
+                    nodes.push(node);
+                    (nodes[nodes.len() - 1].bbox, nodes.len() - 1)
+                })
+                .collect();
+        }
+
+        SpatialIndex {
+            root: level.first().map(|l| l.1),
+            entries,
+            nodes,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Volumes whose bounding boxes pass the test
+    fn search<F: Fn(&BBox) -> bool>(&self, test: F) -> Vec<VolumeRef> {
+        let mut result = Vec::new();
+        let mut stack: Vec<usize> = self.root.into_iter().collect();
+
+        while let Some(n) = stack.pop() {
+            let node = &self.nodes[n];
+            if !test(&node.bbox) {
+                continue;
+            }
+
+            match &node.children {
+                Children::Inner(children) => stack.extend(children),
+                Children::Leaf(children) => result.extend(
+                    children
+                        .iter()
+                        .map(|&e| &self.entries[e])
+                        .filter(|e| test(&e.0))
+                        .map(|e| e.1),
+                ),
+            }
+        }
+
+        result.sort();
+        result
+    }
+
+    // Candidate volumes containing the point
+    pub fn point(&self, p: &LatLon) -> Vec<VolumeRef> {
+        self.search(|bbox| bbox.contains(p))
+    }
+
+    // Candidate volumes crossed by the line segment. Segments are treated
+    // as straight in lat/lon, so should be short, e.g. between GPS fixes
+    pub fn segment(&self, a: &LatLon, b: &LatLon) -> Vec<VolumeRef> {
+        self.search(|bbox| bbox.intersects_line(a, b))
+    }
+
+    // Candidate volumes overlapping the box
+    pub fn bbox(&self, other: &BBox) -> Vec<VolumeRef> {
+        self.search(|bbox| bbox.intersects(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::yaixm::testdata;
+
+    fn latlon(value: &str) -> LatLon {
+        value.parse().unwrap()
+    }
+
+    // Grid of circles, one every 10 minutes
+    fn grid() -> Vec<Feature> {
+        let mut volumes = Vec::new();
+        for i in 0..20 {
+            for j in 0..20 {
+                volumes.push(format!(
+                    r#"{{"lower": "SFC", "upper": "FL100", "boundary":
+                        [{{"circle": {{"centre": "5{}{}000N 00{}{}000W", "radius": "2 nm"}}}}]}}"#,
+                    i / 6,
+                    i % 6,
+                    j / 6,
+                    j % 6
+                ));
+            }
+        }
+        serde_json::from_str(&format!(
+            r#"[{{"name": "GRID", "type": "OTHER", "geometry": [{}]}}]"#,
+            volumes.join(",")
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn bbox() {
+        let volume = &testdata::yaixm().airspace[0].geometry[0];
+        let bbox = BBox::from_volume(volume);
+
+        // 5 nm circle at 514500N 0013500W
+        assert!((bbox.max_lat - (51.75 + 5.0 / 60.0)).abs() < 0.001);
+        assert!((bbox.min_lat - (51.75 - 5.0 / 60.0)).abs() < 0.001);
+        let dlon = 5.0 / 60.0 / 51.75_f64.to_radians().cos();
+        assert!((bbox.max_lon - (-1.5833 + dlon)).abs() < 0.002);
+
+        let a = latlon("514500N 0020000W");
+        let b = latlon("514500N 0010000W");
+        assert!(bbox.intersects_line(&a, &b));
+        assert!(!bbox.intersects_line(&a, &latlon("520000N 0020000W")));
+        assert!(bbox.intersects_line(&latlon("514500N 0013500W"), &a));
+    }
+
+    #[test]
+    fn queries() {
+        let airspace = grid();
+        let index = SpatialIndex::new(&airspace);
+        assert_eq!(index.len(), 400);
+
+        // Each query agrees with a full scan
+        let scan = |test: &dyn Fn(&BBox) -> bool| {
+            airspace[0]
+                .geometry
+                .iter()
+                .enumerate()
+                .filter(|(_, v)| test(&BBox::from_volume(v)))
+                .map(|(v, _)| VolumeRef {
+                    feature: 0,
+                    volume: v,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let p = latlon("511000N 0012000W");
+        assert_eq!(index.point(&p), scan(&|b| b.contains(&p)));
+        assert_eq!(
+            index.point(&p),
+            [VolumeRef {
+                feature: 0,
+                volume: 148
+            }]
+        );
+        assert!(index.point(&latlon("510500N 0010500W")).is_empty());
+
+        let (a, b) = (latlon("510100N 0000100W"), latlon("521000N 0021000W"));
+        assert_eq!(index.segment(&a, &b), scan(&|x| x.intersects_line(&a, &b)));
+        assert_eq!(index.segment(&a, &b).len(), 11);
+
+        // Four rows of six circles
+        let area = BBox::from_points(&[a, latlon("513000N 0005500W")]);
+        assert_eq!(index.bbox(&area), scan(&|x| x.intersects(&area)));
+        assert_eq!(index.bbox(&area).len(), 24);
+
+        // Filtered and empty indices
+        let index = SpatialIndex::with_filter(&airspace, |_, v| v.boundary.len() > 1);
+        assert!(index.is_empty());
+        assert!(index.point(&p).is_empty());
+    }
+}
@@ -0,0 +1,215 @@
+use crate::settings::Settings;
+use crate::yaixm::convert::{airfilter, merged_airspace, volume_name, volume_rules, volume_type};
+use crate::yaixm::geo::LatLon;
+use crate::yaixm::level::Level;
+use crate::yaixm::linearise::{self, Resolution};
+use crate::yaixm::{Feature, Volume, Yaixm};
+use chrono::{DateTime, Utc};
+
+const FEET_TO_METRES: f64 = 0.3048;
+
+// OpenAir types and line colours (KML aabbggrr order)
+const STYLES: [(&str, &str); 17] = [
+    ("A", "ffff0000"),
+    ("B", "ffff0000"),
+    ("C", "ffff0000"),
+    ("D", "ffff0000"),
+    ("E", "ffff0000"),
+    ("F", "ff888888"),
+    ("G", "ff888888"),
+    ("CTA", "ffff0000"),
+    ("CTR", "ffff0000"),
+    ("P", "ff0000ff"),
+    ("R", "ff0055ff"),
+    ("Q", "ff00aaff"),
+    ("W", "ff00aa00"),
+    ("MATZ", "ffaa00aa"),
+    ("RMZ", "ff00dddd"),
+    ("TMZ", "ff00dddd"),
+    ("OTHER", "ff888888"),
+];
+
+// Level in metres. Flight levels are pressure altitudes and are converted
+// using the ICAO standard atmosphere, i.e. with a 1013.25 hPa datum FL100
+// is 10,000 ft (3048 m). No correction is made for the actual QNH or
+// temperature. Heights above ground are drawn as if the ground was at sea
+// level
+fn level_metres(level: &Level) -> f64 {
+    level.feet() as f64 * FEET_TO_METRES
+}
+
+// Escape XML special characters
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn do_styles() -> String {
+    STYLES
+        .iter()
+        .map(|(id, colour)| {
+            format!(
+                "<Style id=\"{}\">\
+                 <LineStyle><color>{}</color><width>1.5</width></LineStyle>\
+                 <PolyStyle><color>40{}</color></PolyStyle>\
+                 </Style>\n",
+                id,
+                colour,
+                &colour[2..]
+            )
+        })
+        .collect()
+}
+
+fn do_polygon(ring: &[(LatLon, f64)]) -> String {
+    let coords = ring
+        .iter()
+        .map(|(p, alt)| format!("{:.6},{:.6},{:.0}", p.lon, p.lat, alt))
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    format!(
+        "<Polygon><altitudeMode>absolute</altitudeMode>\
+         <outerBoundaryIs><LinearRing><coordinates>{}</coordinates></LinearRing></outerBoundaryIs>\
+         </Polygon>\n",
+        coords
+    )
+}
+
+// Closed volume, top and bottom surfaces joined by vertical walls
+fn do_geometry(volume: &Volume, resolution: Resolution) -> String {
+    let points = linearise::volume(volume, resolution);
+    let lower = level_metres(&volume.lower);
+    let upper = level_metres(&volume.upper);
+
+    let mut out = "<MultiGeometry>\n".to_string();
+    for alt in [upper, lower] {
+        out.push_str(&do_polygon(
+            &points.iter().map(|p| (*p, alt)).collect::<Vec<_>>(),
+        ));
+    }
+    for wall in points.windows(2) {
+        out.push_str(&do_polygon(&[
+            (wall[0], lower),
+            (wall[1], lower),
+            (wall[1], upper),
+            (wall[0], upper),
+            (wall[0], lower),
+        ]));
+    }
+    out.push_str("</MultiGeometry>\n");
+    out
+}
+
+fn do_description(volume: &Volume, openair_type: &str, rules: &[&str]) -> String {
+    let mut desc = format!(
+        "Class: {}<br/>Upper: {}<br/>Lower: {}",
+        openair_type, volume.upper, volume.lower
+    );
+    if let Some(freq) = volume.frequency {
+        desc.push_str(&format!("<br/>Frequency: {:.3}", freq));
+    }
+    if !rules.is_empty() {
+        desc.push_str(&format!("<br/>Rules: {}", rules.join(", ")));
+    }
+
+    format!("<description><![CDATA[{}]]></description>\n", desc)
+}
+
+fn do_placemark(
+    feature: &Feature,
+    volume: &Volume,
+    n: usize,
+    settings: &Settings,
+    resolution: Resolution,
+) -> String {
+    let openair_type = volume_type(feature, volume, settings);
+
+    let rules = volume_rules(feature, volume);
+
+    format!(
+        "<Placemark>\n<name>{}</name>\n{}<styleUrl>#{}</styleUrl>\n{}</Placemark>\n",
+        escape(&volume_name(feature, volume, n, settings)),
+        do_description(volume, openair_type, &rules),
+        openair_type,
+        do_geometry(volume, resolution)
+    )
+}
+
+// Generate KML data, each volume is drawn as a solid from its lower to its
+// upper level. Arcs and circles are converted to points at the given
+// resolution
+pub fn kml(
+    yaixm: &Yaixm,
+    settings: &Settings,
+    produced: DateTime<Utc>,
+    resolution: Resolution,
+) -> String {
+    let rel = &yaixm.release;
+
+    let mut output = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <kml xmlns=\"http://www.opengis.net/kml/2.2\">\n\
+        <Document>\n\
+        <name>UK Airspace</name>\n"
+        .to_string();
+    let profile = match &settings.profile {
+        Some(name) => format!(", Profile: {}", escape(name)),
+        None => String::new(),
+    };
+    output.push_str(&format!(
+        "<description>{} AIRAC: {}, Commit: {}, Produced: {}{}</description>\n",
+        escape(&rel.note),
+        &rel.airac_date[..10],
+        escape(&rel.commit),
+        produced.to_rfc3339(),
+        profile
+    ));
+    output.push_str(&do_styles());
+
+    for feature in merged_airspace(yaixm, settings) {
+        for (n, volume) in feature.geometry.iter().enumerate() {
+            if airfilter(&feature, volume, settings) {
+                output.push_str(&do_placemark(&feature, volume, n, settings, resolution));
+            }
+        }
+    }
+
+    output.push_str("</Document>\n</kml>\n");
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Format;
+    use crate::yaixm::testdata::yaixm;
+
+    #[test]
+    fn levels() {
+        assert_eq!(level_metres(&Level::Surface), 0.0);
+        assert!((level_metres(&Level::FlightLevel(100)) - 3048.0).abs() < 1e-9);
+        assert!((level_metres(&Level::Altitude(3500)) - 1066.8).abs() < 1e-9);
+    }
+
+    #[test]
+    fn placemarks() {
+        let mut settings = Settings::default();
+        settings.options.format = Format::Kml;
+        settings.rat.insert("ROYAL FLIGHT".to_string());
+
+        let kml = kml(&yaixm(), &settings, Utc::now(), Resolution::Step(30.0));
+        assert!(kml.starts_with("<?xml"));
+        assert!(kml.ends_with("</kml>\n"));
+        assert_eq!(kml.matches("<Placemark>").count(), 3);
+        assert!(kml.contains("<name>BRIZE NORTON</name>"));
+        assert!(kml.contains("Class: D<br/>Upper: 3500 ft<br/>Lower: SFC<br/>Frequency: 119.000"));
+        assert!(kml.contains("<styleUrl>#P</styleUrl>"));
+
+        // 12 point circle gives top, bottom and 12 walls
+        let brize = &kml[kml.find("BRIZE").unwrap()..kml.find("ROYAL").unwrap()];
+        assert_eq!(brize.matches("<Polygon>").count(), 14);
+        assert!(brize.contains(",1067 "));
+    }
+}
@@ -0,0 +1,142 @@
+use serde::de::{self, Deserialize, Deserializer};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+// Vertical limit of an airspace volume
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Level {
+    Surface,
+    // Flight level, i.e. pressure altitude in hundreds of feet
+    FlightLevel(u16),
+    // Feet above mean sea level
+    Altitude(u32),
+    // Feet above ground level
+    Height(u32),
+}
+
+impl Level {
+    // Approximate altitude in feet, assuming standard pressure and with the
+    // ground at sea level
+    pub fn feet(&self) -> u32 {
+        match self {
+            Level::Surface => 0,
+            Level::FlightLevel(fl) => *fl as u32 * 100,
+            Level::Altitude(alt) => *alt,
+            Level::Height(hgt) => *hgt,
+        }
+    }
+
+    pub fn is_surface(&self) -> bool {
+        *self == Level::Surface
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Level::Surface => 0,
+            Level::Height(_) => 1,
+            Level::Altitude(_) => 2,
+            Level::FlightLevel(_) => 3,
+        }
+    }
+}
+
+// Levels are ordered by their approximate altitude (see `Level::feet`), with
+// ties broken by the type of level
+impl Ord for Level {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.feet()
+            .cmp(&other.feet())
+            .then(self.rank().cmp(&other.rank()))
+    }
+}
+
+impl PartialOrd for Level {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// YAIXM level, e.g. "SFC", "FL195", "3500 ft" or "1500 ft agl"
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let err = || format!("invalid level \"{}\"", value);
+        let digits = |x: &str| !x.is_empty() && x.bytes().all(|b| b.is_ascii_digit());
+
+        if value == "SFC" {
+            Ok(Level::Surface)
+        } else if let Some(fl) = value.strip_prefix("FL").filter(|x| digits(x)) {
+            fl.parse().map(Level::FlightLevel).map_err(|_| err())
+        } else if let Some(hgt) = value
+            .strip_suffix(" ft agl")
+            .or_else(|| value.strip_suffix(" ft AGL"))
+            .filter(|x| digits(x))
+        {
+            hgt.parse().map(Level::Height).map_err(|_| err())
+        } else if let Some(alt) = value.strip_suffix(" ft").filter(|x| digits(x)) {
+            alt.parse().map(Level::Altitude).map_err(|_| err())
+        } else {
+            Err(err())
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Level::Surface => write!(f, "SFC"),
+            Level::FlightLevel(fl) => write!(f, "FL{}", fl),
+            Level::Altitude(alt) => write!(f, "{} ft", alt),
+            Level::Height(hgt) => write!(f, "{} ft agl", hgt),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Level {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        for (text, level) in [
+            ("SFC", Level::Surface),
+            ("FL65", Level::FlightLevel(65)),
+            ("FL195", Level::FlightLevel(195)),
+            ("3500 ft", Level::Altitude(3500)),
+            ("1500 ft agl", Level::Height(1500)),
+        ] {
+            assert_eq!(text.parse::<Level>(), Ok(level));
+            assert_eq!(level.to_string(), text);
+        }
+        assert_eq!("1500 ft AGL".parse::<Level>(), Ok(Level::Height(1500)));
+
+        for bad in [
+            "", "sfc", "FL", "FL-1", "FL99999", "ft", " ft", "3500", "3500ft", "x ft",
+        ] {
+            assert!(bad.parse::<Level>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn ordering() {
+        assert!(Level::Surface < Level::Height(500));
+        assert!(Level::Height(500) < Level::Altitude(1000));
+        assert!(Level::Altitude(6000) < Level::FlightLevel(65));
+        assert!(Level::Altitude(6500) < Level::FlightLevel(65));
+        assert!(Level::FlightLevel(65) < Level::Altitude(7000));
+        assert_eq!(
+            Level::FlightLevel(195).max(Level::Altitude(19000)),
+            Level::FlightLevel(195)
+        );
+    }
+}
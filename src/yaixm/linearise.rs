@@ -0,0 +1,213 @@
+//! Conversion of arcs and circles to points, for consumers which can only
+//! handle polygons.
+//!
+//! Arcs and circles are loci of constant great circle distance from their
+//! centre on a spherical earth.
+
+use crate::yaixm::geo::{sweep, LatLon};
+use crate::yaixm::{Boundary, Direction, Volume};
+
+// Smallest angle (degrees) between points, regardless of resolution
+const MIN_STEP: f64 = 0.1;
+
+// Default resolution for exported polygons
+pub const DEFAULT_RESOLUTION: Resolution = Resolution::Step(5.0);
+
+// Spacing of points on arcs and circles
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Resolution {
+    // Maximum angle (degrees) subtended at the centre by each chord
+    Step(f64),
+    // Maximum distance (nautical miles) between each chord and the arc
+    ChordError(f64),
+}
+
+impl Resolution {
+    // Angular step (degrees) for an arc of given radius (nautical miles)
+    pub fn step(&self, radius: f64) -> f64 {
+        let step = match *self {
+            Resolution::Step(step) => step,
+            Resolution::ChordError(error) => {
+                // Chord error, r(1 - cos(step / 2)), is largest mid-chord
+                let ratio = (1.0 - error / radius).clamp(-1.0, 1.0);
+                2.0 * ratio.acos().to_degrees()
+            }
+        };
+        step.max(MIN_STEP)
+    }
+}
+
+// Points along an arc, excluding the start point and including the end
+// point
+pub fn arc(
+    centre: &LatLon,
+    radius: f64,
+    from: &LatLon,
+    to: &LatLon,
+    clockwise: bool,
+    resolution: Resolution,
+) -> Vec<LatLon> {
+    let start = centre.bearing(from);
+    let angle = sweep(start, centre.bearing(to), clockwise);
+    let n = (angle / resolution.step(radius)).ceil().max(1.0) as usize;
+    let delta = if clockwise { angle } else { -angle } / n as f64;
+
+    let mut points = (1..n)
+        .map(|i| centre.destination(start + delta * i as f64, radius))
+        .collect::<Vec<LatLon>>();
+    points.push(*to);
+    points
+}
+
+// Closed ring of points around a circle, starting due north and going
+// clockwise
+pub fn circle(centre: &LatLon, radius: f64, resolution: Resolution) -> Vec<LatLon> {
+    let n = (360.0 / resolution.step(radius)).ceil().max(3.0) as usize;
+    let mut points = (0..n)
+        .map(|i| centre.destination(360.0 * i as f64 / n as f64, radius))
+        .collect::<Vec<LatLon>>();
+    points.push(points[0]);
+    points
+}
+
+// Closed ring of points around a boundary
+pub fn boundary(boundary: &[Boundary], resolution: Resolution) -> Vec<LatLon> {
+    let mut points: Vec<LatLon> = Vec::new();
+
+    for segment in boundary {
+        match segment {
+            Boundary::Line(line) => points.extend(line),
+            Boundary::Arc(a) => {
+                let from = *points.last().unwrap();
+                points.extend(arc(
+                    &a.centre,
+                    a.radius.nm(),
+                    &from,
+                    &a.to,
+                    a.dir == Direction::Cw,
+                    resolution,
+                ));
+            }
+            Boundary::Circle(c) => return circle(&c.centre, c.radius.nm(), resolution),
+        }
+    }
+
+    // Close the ring
+    if let (Some(first), Some(last)) = (points.first(), points.last()) {
+        if first != last {
+            points.push(*first);
+        }
+    }
+
+    points
+}
+
+// Closed ring of points around a volume
+pub fn volume(volume: &Volume, resolution: Resolution) -> Vec<LatLon> {
+    boundary(&volume.boundary, resolution)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64, tol: f64) -> bool {
+        (a - b).abs() < tol
+    }
+
+    // Largest distance from arc to chord mid-points
+    fn max_error(centre: &LatLon, radius: f64, points: &[LatLon]) -> f64 {
+        points
+            .windows(2)
+            .map(|w| {
+                let mid = w[0].destination(w[0].bearing(&w[1]), w[0].distance(&w[1]) / 2.0);
+                radius - centre.distance(&mid)
+            })
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn step() {
+        assert_eq!(Resolution::Step(10.0).step(5.0), 10.0);
+        assert_eq!(Resolution::Step(0.0).step(5.0), MIN_STEP);
+
+        // Chord error of 1 - cos(30) subtends 60 degrees
+        let step = Resolution::ChordError(1.0 - 30f64.to_radians().cos()).step(1.0);
+        assert!(close(step, 60.0, 1e-9));
+
+        // Error larger than the radius
+        assert_eq!(Resolution::ChordError(2.0).step(1.0), 360.0);
+        assert_eq!(Resolution::ChordError(0.1).step(0.0), 360.0);
+    }
+
+    #[test]
+    fn arc_direction() {
+        let centre = LatLon {
+            lat: 52.0,
+            lon: -1.0,
+        };
+        let from = centre.destination(90.0, 10.0);
+        let to = centre.destination(270.0, 10.0);
+
+        // Anti-clockwise via north
+        let points = arc(&centre, 10.0, &from, &to, false, Resolution::Step(10.0));
+        assert_eq!(points.len(), 18);
+        assert_eq!(*points.last().unwrap(), to);
+        assert!(points.iter().all(|p| close(centre.distance(p), 10.0, 1e-6)));
+        assert!(points.iter().all(|p| p.lat > 51.99));
+
+        // Clockwise via south
+        let points = arc(&centre, 10.0, &from, &to, true, Resolution::Step(10.0));
+        assert!(points.iter().all(|p| p.lat < 52.0));
+    }
+
+    #[test]
+    fn chord_error() {
+        let centre = LatLon {
+            lat: 55.0,
+            lon: -3.0,
+        };
+        let from = centre.destination(10.0, 20.0);
+        let to = centre.destination(200.0, 20.0);
+
+        for error in [0.5, 0.1, 0.01] {
+            let mut points = vec![from];
+            points.extend(arc(
+                &centre,
+                20.0,
+                &from,
+                &to,
+                true,
+                Resolution::ChordError(error),
+            ));
+            let e = max_error(&centre, 20.0, &points);
+            assert!(e <= error * 1.001, "{} {}", error, e);
+            assert!(e > error * 0.5, "{} {}", error, e);
+
+            let points = circle(&centre, 20.0, Resolution::ChordError(error));
+            assert!(max_error(&centre, 20.0, &points) <= error * 1.001);
+        }
+    }
+
+    #[test]
+    fn rings() {
+        let centre = LatLon {
+            lat: 52.0,
+            lon: -1.0,
+        };
+        let points = circle(&centre, 5.0, Resolution::Step(30.0));
+        assert_eq!(points.len(), 13);
+        assert_eq!(points.first(), points.last());
+        assert!(points.iter().all(|p| close(centre.distance(p), 5.0, 1e-6)));
+
+        let boundary: Vec<Boundary> = serde_json::from_str(
+            r#"[{"line": ["544800N 0020000W", "544800N 0014239W"]},
+                {"arc": {"centre": "544800N 0020000W", "dir": "ccw",
+                         "radius": "10 nm", "to": "544800N 0021721W"}}]"#,
+        )
+        .unwrap();
+        let points = super::boundary(&boundary, Resolution::Step(10.0));
+        assert_eq!(points.len(), 21);
+        assert_eq!(points.first(), points.last());
+    }
+}
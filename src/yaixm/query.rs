@@ -0,0 +1,200 @@
+//! Find the selected airspace containing a point.
+
+use crate::settings::Settings;
+use crate::yaixm::convert::{airfilter, merged_airspace, volume_name, volume_rules, volume_type};
+use crate::yaixm::geo::LatLon;
+use crate::yaixm::level::Level;
+use crate::yaixm::shape::{Point, Projection, Shape};
+use crate::yaixm::{Volume, Yaixm};
+
+// Standard pressure and the (ISA, near sea level) change of pressure
+// altitude with pressure
+pub const STANDARD_QNH: f64 = 1013.25;
+const FEET_PER_HPA: f64 = 27.3;
+
+// Altitude in feet, as pressure altitude (on 1013.25 hPa) and on QNH.
+// There is no terrain model, so heights above ground are compared with
+// altitude on QNH
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Altitude {
+    pub pressure: f64,
+    pub qnh: f64,
+}
+
+impl Altitude {
+    // Altitude (feet) on the given QNH
+    pub fn from_qnh(altitude: f64, qnh: f64) -> Altitude {
+        Altitude {
+            pressure: altitude - (qnh - STANDARD_QNH) * FEET_PER_HPA,
+            qnh: altitude,
+        }
+    }
+
+    // Pressure altitude (feet) with the given QNH
+    pub fn from_pressure(pressure: f64, qnh: f64) -> Altitude {
+        Altitude {
+            pressure,
+            qnh: pressure + (qnh - STANDARD_QNH) * FEET_PER_HPA,
+        }
+    }
+
+    // Feet above (positive) or below the level
+    pub fn above(&self, level: &Level) -> f64 {
+        match level {
+            Level::Surface => f64::INFINITY,
+            Level::FlightLevel(_) => self.pressure - level.feet() as f64,
+            Level::Altitude(_) | Level::Height(_) => self.qnh - level.feet() as f64,
+        }
+    }
+}
+
+// Feet inside the vertical limits, negative if outside
+pub fn vertical_margin(volume: &Volume, altitude: &Altitude) -> f64 {
+    altitude
+        .above(&volume.lower)
+        .min(-altitude.above(&volume.upper))
+}
+
+// Nautical miles inside the boundary, None if outside
+pub fn horizontal_margin(volume: &Volume, position: &LatLon) -> Option<f64> {
+    let shape = Shape::new(&volume.boundary, &Projection::new(*position));
+    let origin = Point { x: 0.0, y: 0.0 };
+    shape
+        .contains(&origin)
+        .then(|| shape.boundary_distance(&origin))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VolumeInfo {
+    pub name: String,
+    pub class: &'static str,
+    pub lower: Level,
+    pub upper: Level,
+    pub frequency: Option<f64>,
+    pub rules: Vec<&'static str>,
+}
+
+// Selected volumes containing the point
+pub fn query(
+    yaixm: &Yaixm,
+    settings: &Settings,
+    position: &LatLon,
+    altitude: &Altitude,
+) -> Vec<VolumeInfo> {
+    let mut result = Vec::new();
+    for feature in merged_airspace(yaixm, settings) {
+        for (n, volume) in feature.geometry.iter().enumerate() {
+            if airfilter(&feature, volume, settings)
+                && vertical_margin(volume, altitude) > 0.0
+                && horizontal_margin(volume, position).is_some()
+            {
+                result.push(VolumeInfo {
+                    name: volume_name(&feature, volume, n, settings),
+                    class: volume_type(&feature, volume, settings),
+                    lower: volume.lower,
+                    upper: volume.upper,
+                    frequency: volume.frequency,
+                    rules: volume_rules(&feature, volume),
+                });
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::yaixm::testdata;
+
+    fn latlon(value: &str) -> LatLon {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn altitude() {
+        let alt = Altitude::from_qnh(3000.0, 1003.25);
+        assert!((alt.pressure - 3273.0).abs() < 1e-9);
+        assert_eq!(Altitude::from_pressure(alt.pressure, 1003.25), alt);
+
+        assert_eq!(alt.above(&Level::Surface), f64::INFINITY);
+        assert!((alt.above(&Level::FlightLevel(30)) - 273.0).abs() < 1e-9);
+        assert_eq!(alt.above(&Level::Altitude(3500)), -500.0);
+        assert_eq!(alt.above(&Level::Height(2000)), 1000.0);
+    }
+
+    #[test]
+    fn arc_boundary() {
+        // Square with a semicircular (5 nm radius) eastern side
+        let volume: Volume = serde_json::from_str(
+            r#"{"lower": "SFC", "upper": "FL100",
+                "boundary": [
+                  {"line": ["510000N 0010000W", "511000N 0010000W", "511000N 0004000W"]},
+                  {"arc": {"centre": "510500N 0004000W", "dir": "cw",
+                           "radius": "5 nm", "to": "510000N 0004000W"}}]}"#,
+        )
+        .unwrap();
+
+        // 4.9 nm and 5.1 nm east of the arc centre, close to its chord error
+        let east = |nm: f64| LatLon {
+            lat: 51.0 + 5.0 / 60.0,
+            lon: -40.0 / 60.0 + nm / (60.0 * (51.0 + 5.0 / 60.0_f64).to_radians().cos()),
+        };
+        let margin = horizontal_margin(&volume, &east(4.9)).unwrap();
+        assert!((margin - 0.1).abs() < 0.005);
+        assert!(horizontal_margin(&volume, &east(5.1)).is_none());
+
+        // Inside the square and outside the west side
+        assert!(horizontal_margin(&volume, &latlon("510500N 0005000W")).is_some());
+        assert!(horizontal_margin(&volume, &latlon("510500N 0010100W")).is_none());
+    }
+
+    #[test]
+    fn query_point() {
+        let yaixm = testdata::yaixm();
+        let mut settings = Settings::default();
+        let centre = latlon("514500N 0013500W");
+
+        let result = query(
+            &yaixm,
+            &settings,
+            &centre,
+            &Altitude::from_qnh(2000.0, 1013.25),
+        );
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].name, "BRIZE NORTON");
+        assert_eq!(result[0].class, "D");
+        assert_eq!(result[0].upper, Level::Altitude(3500));
+        assert_eq!(result[0].frequency, Some(119.0));
+
+        // Above the top, or outside the circle
+        assert!(query(
+            &yaixm,
+            &settings,
+            &centre,
+            &Altitude::from_qnh(4000.0, 1013.25)
+        )
+        .is_empty());
+        let outside = latlon("515100N 0013500W");
+        assert!(query(
+            &yaixm,
+            &settings,
+            &outside,
+            &Altitude::from_qnh(2000.0, 1013.25)
+        )
+        .is_empty());
+
+        // Selected RA(T), lower volume only
+        settings.rat.insert("ROYAL FLIGHT".to_string());
+        let royal = latlon("520000N 0010000W");
+        let result = query(
+            &yaixm,
+            &settings,
+            &royal,
+            &Altitude::from_pressure(2000.0, 1013.25),
+        );
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].upper, Level::FlightLevel(45));
+        assert_eq!(result[0].frequency, Some(123.45));
+    }
+}
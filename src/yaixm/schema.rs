@@ -0,0 +1,138 @@
+//! YAIXM schema version checks and migration.
+//!
+//! Documents newer than `SCHEMA_VERSION` are refused, since they may hold
+//! data the `Yaixm` structs would silently misread. Older documents, back
+//! to `MIN_SCHEMA_VERSION`, are upgraded one version at a time on the raw
+//! JSON before deserializing.
+
+use crate::yaixm::Yaixm;
+use serde_json::Value;
+
+// Schema version of the Yaixm structs
+pub const SCHEMA_VERSION: u64 = 1;
+
+// Oldest schema version that can be migrated
+pub const MIN_SCHEMA_VERSION: u64 = 1;
+
+// Upgrade document from one schema version to the next
+type Migration = fn(&mut Value) -> Result<(), String>;
+
+// Migrations from MIN_SCHEMA_VERSION up to SCHEMA_VERSION
+const MIGRATIONS: &[Migration] = &[];
+
+fn schema_version(value: &Value) -> Result<u64, String> {
+    value
+        .pointer("/release/schema_version")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| "missing YAIXM release schema_version".to_string())
+}
+
+// Apply migrations, the first of which upgrades from version `min`
+fn migrate(value: &mut Value, min: u64, migrations: &[Migration]) -> Result<(), String> {
+    let current = min + migrations.len() as u64;
+    let version = schema_version(value)?;
+
+    if version > current {
+        return Err(format!(
+            "YAIXM schema version {} is newer than supported version {}",
+            version, current
+        ));
+    }
+    if version < min {
+        return Err(format!(
+            "YAIXM schema version {} is older than supported versions {} to {}",
+            version, min, current
+        ));
+    }
+
+    for (from, migration) in (version..).zip(&migrations[(version - min) as usize..]) {
+        migration(value)
+            .map_err(|e| format!("can't migrate YAIXM schema version {}: {}", from, e))?;
+        value["release"]["schema_version"] = Value::from(from + 1);
+    }
+    Ok(())
+}
+
+// Check, migrate and deserialize YAIXM JSON
+pub fn from_value(mut value: Value) -> Result<Yaixm, String> {
+    migrate(&mut value, MIN_SCHEMA_VERSION, MIGRATIONS)?;
+    serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+pub fn from_str(data: &str) -> Result<Yaixm, String> {
+    from_value(serde_json::from_str(data).map_err(|e| e.to_string())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::yaixm::testdata;
+
+    fn with_version(version: u64) -> Value {
+        let mut value: Value = serde_json::from_str(testdata::YAIXM).unwrap();
+        value["release"]["schema_version"] = Value::from(version);
+        value
+    }
+
+    #[test]
+    fn supported() {
+        assert_eq!(MIGRATIONS.len() as u64, SCHEMA_VERSION - MIN_SCHEMA_VERSION);
+
+        let yaixm = from_str(testdata::YAIXM).unwrap();
+        assert_eq!(yaixm.release.schema_version as u64, SCHEMA_VERSION);
+
+        let err = from_value(with_version(SCHEMA_VERSION + 1)).unwrap_err();
+        assert!(err.contains("newer than supported"), "{}", err);
+
+        let mut value = with_version(1);
+        value["release"]
+            .as_object_mut()
+            .unwrap()
+            .remove("schema_version");
+        assert!(from_value(value).unwrap_err().contains("missing"));
+    }
+
+    #[test]
+    fn migration_chain() {
+        // Version 1 used "seq" rather than "seqno", and version 2 lacked
+        // the services list
+        fn v1_to_v2(value: &mut Value) -> Result<(), String> {
+            for feature in value["airspace"].as_array_mut().ok_or("no airspace")? {
+                for volume in feature["geometry"].as_array_mut().ok_or("no geometry")? {
+                    if let Some(seq) = volume.as_object_mut().unwrap().remove("seq") {
+                        volume["seqno"] = seq;
+                    }
+                }
+            }
+            Ok(())
+        }
+        fn v2_to_v3(value: &mut Value) -> Result<(), String> {
+            value["service"] = Value::Array(vec![]);
+            Ok(())
+        }
+        let migrations: &[Migration] = &[v1_to_v2, v2_to_v3];
+
+        let mut value = with_version(1);
+        value["airspace"][0]["geometry"][0]["seq"] = Value::from(2);
+        value.as_object_mut().unwrap().remove("service");
+        migrate(&mut value, 1, migrations).unwrap();
+        assert_eq!(value["release"]["schema_version"], 3);
+        assert_eq!(value["airspace"][0]["geometry"][0]["seqno"], 2);
+        assert_eq!(value["service"], Value::Array(vec![]));
+
+        // Part way along the chain
+        let mut value = with_version(2);
+        value.as_object_mut().unwrap().remove("service");
+        migrate(&mut value, 1, migrations).unwrap();
+        assert_eq!(value["release"]["schema_version"], 3);
+
+        // Too old, and a failed migration
+        let err = migrate(&mut with_version(0), 1, migrations).unwrap_err();
+        assert!(err.contains("older than supported"), "{}", err);
+
+        let mut value = with_version(1);
+        value["airspace"] = Value::Null;
+        let err = migrate(&mut value, 1, migrations).unwrap_err();
+        assert_eq!(err, "can't migrate YAIXM schema version 1: no airspace");
+    }
+}
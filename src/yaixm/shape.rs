@@ -0,0 +1,463 @@
+//! Exact planar geometry of volume boundaries.
+//!
+//! Boundaries are projected onto a local plane, in nautical miles, centred
+//! near the position of interest. Within the plane lines, arcs and circles
+//! are handled exactly, without converting arcs to points. The projection
+//! error is about 1% at 50 nm from the origin.
+
+use crate::yaixm::geo::{sweep, LatLon};
+use crate::yaixm::{Boundary, Direction};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    fn sub(&self, other: &Point) -> Point {
+        Point {
+            x: self.x - other.x,
+            y: self.y - other.y,
+        }
+    }
+
+    fn dot(&self, other: &Point) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+
+    fn cross(&self, other: &Point) -> f64 {
+        self.x * other.y - self.y * other.x
+    }
+
+    fn length(&self) -> f64 {
+        self.x.hypot(self.y)
+    }
+
+    fn distance(&self, other: &Point) -> f64 {
+        self.sub(other).length()
+    }
+
+    // Bearing (degrees, clockwise from north) from the origin
+    fn bearing(&self) -> f64 {
+        self.x.atan2(self.y).to_degrees().rem_euclid(360.0)
+    }
+}
+
+// Equirectangular projection, nautical miles east and north of the origin
+#[derive(Clone, Copy, Debug)]
+pub struct Projection {
+    origin: LatLon,
+    scale: f64,
+}
+
+impl Projection {
+    pub fn new(origin: LatLon) -> Projection {
+        Projection {
+            origin,
+            scale: origin.lat.to_radians().cos(),
+        }
+    }
+
+    pub fn project(&self, latlon: &LatLon) -> Point {
+        Point {
+            x: (latlon.lon - self.origin.lon) * self.scale * 60.0,
+            y: (latlon.lat - self.origin.lat) * 60.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct PlaneArc {
+    centre: Point,
+    radius: f64,
+    start: Point,
+    end: Point,
+    clockwise: bool,
+}
+
+impl PlaneArc {
+    fn start_bearing(&self) -> f64 {
+        self.start.sub(&self.centre).bearing()
+    }
+
+    fn sweep(&self) -> f64 {
+        sweep(
+            self.start_bearing(),
+            self.end.sub(&self.centre).bearing(),
+            self.clockwise,
+        )
+    }
+
+    // Test if direction from the centre to `p` lies within the arc
+    fn spans(&self, p: &Point) -> bool {
+        let brg = p.sub(&self.centre).bearing();
+        sweep(self.start_bearing(), brg, self.clockwise) <= self.sweep()
+    }
+
+    fn midpoint(&self) -> Point {
+        let half = self.sweep() / 2.0;
+        let brg = self.start_bearing() + if self.clockwise { half } else { -half };
+        let brg = brg.to_radians();
+        Point {
+            x: self.centre.x + self.radius * brg.sin(),
+            y: self.centre.y + self.radius * brg.cos(),
+        }
+    }
+
+    // Test if `p` is in the region between the arc and its chord
+    fn segment_contains(&self, p: &Point) -> bool {
+        let chord = self.end.sub(&self.start);
+        if p.distance(&self.centre) >= self.radius || chord.length() == 0.0 {
+            return false;
+        }
+
+        let side = chord.cross(&p.sub(&self.start));
+        let arc_side = chord.cross(&self.midpoint().sub(&self.start));
+        side * arc_side > 0.0
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Segment {
+    Line(Point, Point),
+    Arc(PlaneArc),
+    Circle(Point, f64),
+}
+
+// Distance from point to line segment
+fn point_line(p: &Point, a: &Point, b: &Point) -> f64 {
+    let ab = b.sub(a);
+    let len2 = ab.dot(&ab);
+    let t = if len2 == 0.0 {
+        0.0
+    } else {
+        (p.sub(a).dot(&ab) / len2).clamp(0.0, 1.0)
+    };
+    p.distance(&Point {
+        x: a.x + t * ab.x,
+        y: a.y + t * ab.y,
+    })
+}
+
+// Distance from point to arc
+fn point_arc(p: &Point, arc: &PlaneArc) -> f64 {
+    if arc.spans(p) {
+        (p.distance(&arc.centre) - arc.radius).abs()
+    } else {
+        p.distance(&arc.start).min(p.distance(&arc.end))
+    }
+}
+
+// Test if line segments ab and cd intersect
+fn lines_intersect(a: &Point, b: &Point, c: &Point, d: &Point) -> bool {
+    let ab = b.sub(a);
+    let cd = d.sub(c);
+    let d1 = ab.cross(&c.sub(a));
+    let d2 = ab.cross(&d.sub(a));
+    let d3 = cd.cross(&a.sub(c));
+    let d4 = cd.cross(&b.sub(c));
+
+    if d1 * d2 < 0.0 && d3 * d4 < 0.0 {
+        return true;
+    }
+
+    // Collinear and touching cases
+    (d1 == 0.0 && point_line(c, a, b) == 0.0)
+        || (d2 == 0.0 && point_line(d, a, b) == 0.0)
+        || (d3 == 0.0 && point_line(a, c, d) == 0.0)
+        || (d4 == 0.0 && point_line(b, c, d) == 0.0)
+}
+
+// Distance between line segments ab and cd
+fn line_line(a: &Point, b: &Point, c: &Point, d: &Point) -> f64 {
+    if lines_intersect(a, b, c, d) {
+        0.0
+    } else {
+        point_line(a, c, d)
+            .min(point_line(b, c, d))
+            .min(point_line(c, a, b))
+            .min(point_line(d, a, b))
+    }
+}
+
+// Distance between line segment ab and a circle
+fn line_circle(a: &Point, b: &Point, centre: &Point, radius: f64) -> f64 {
+    let near = point_line(centre, a, b);
+    let far = a.distance(centre).max(b.distance(centre));
+
+    if near > radius {
+        near - radius
+    } else if far < radius {
+        radius - far
+    } else {
+        0.0
+    }
+}
+
+// Distance between line segment ab and an arc. The closest points are
+// either an end point of one of them, an intersection, or lie on the
+// perpendicular from the arc centre to the line
+fn line_arc(a: &Point, b: &Point, arc: &PlaneArc) -> f64 {
+    let ab = b.sub(a);
+    let ac = a.sub(&arc.centre);
+
+    // Intersections of the line with the arc's circle
+    let qa = ab.dot(&ab);
+    let qb = 2.0 * ab.dot(&ac);
+    let qc = ac.dot(&ac) - arc.radius * arc.radius;
+    let disc = qb * qb - 4.0 * qa * qc;
+    if qa > 0.0 && disc >= 0.0 {
+        for t in [
+            (-qb - disc.sqrt()) / (2.0 * qa),
+            (-qb + disc.sqrt()) / (2.0 * qa),
+        ] {
+            let q = Point {
+                x: a.x + t * ab.x,
+                y: a.y + t * ab.y,
+            };
+            if (0.0..=1.0).contains(&t) && arc.spans(&q) {
+                return 0.0;
+            }
+        }
+    }
+
+    let mut dist = point_arc(a, arc)
+        .min(point_arc(b, arc))
+        .min(point_line(&arc.start, a, b))
+        .min(point_line(&arc.end, a, b));
+
+    // Foot of the perpendicular from the centre
+    if qa > 0.0 {
+        let t = (-ac.dot(&ab) / qa).clamp(0.0, 1.0);
+        let foot = Point {
+            x: a.x + t * ab.x,
+            y: a.y + t * ab.y,
+        };
+        if foot != arc.centre && arc.spans(&foot) {
+            dist = dist.min((foot.distance(&arc.centre) - arc.radius).abs());
+        }
+    }
+
+    dist
+}
+
+// Ray (in the +x direction) from p crosses line segment ab
+fn ray_crosses(p: &Point, a: &Point, b: &Point) -> bool {
+    (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) * (b.x - a.x) / (b.y - a.y)
+}
+
+// Volume boundary in the plane
+#[derive(Clone, Debug)]
+pub struct Shape {
+    segments: Vec<Segment>,
+}
+
+impl Shape {
+    pub fn new(boundary: &[Boundary], projection: &Projection) -> Shape {
+        let mut segments = Vec::new();
+        let mut first = None;
+        let mut prev: Option<Point> = None;
+
+        // Join segment to the end of the previous segment
+        let join = |segments: &mut Vec<Segment>, prev: Option<Point>, next: Point| {
+            if let Some(prev) = prev {
+                if prev != next {
+                    segments.push(Segment::Line(prev, next));
+                }
+            }
+        };
+
+        for segment in boundary {
+            match segment {
+                Boundary::Line(line) => {
+                    for point in line {
+                        let point = projection.project(point);
+                        join(&mut segments, prev, point);
+                        first = first.or(Some(point));
+                        prev = Some(point);
+                    }
+                }
+                Boundary::Arc(arc) => {
+                    let start = prev.unwrap();
+                    let end = projection.project(&arc.to);
+                    segments.push(Segment::Arc(PlaneArc {
+                        centre: projection.project(&arc.centre),
+                        radius: arc.radius.nm(),
+                        start,
+                        end,
+                        clockwise: arc.dir == Direction::Cw,
+                    }));
+                    prev = Some(end);
+                }
+                Boundary::Circle(circle) => {
+                    segments.push(Segment::Circle(
+                        projection.project(&circle.centre),
+                        circle.radius.nm(),
+                    ));
+                }
+            }
+        }
+
+        // Close the boundary
+        if let Some(first) = first {
+            join(&mut segments, prev, first);
+        }
+
+        Shape { segments }
+    }
+
+    // Test if point is inside the boundary. Arcs are treated as their
+    // chord, with the region between the arc and the chord toggling the
+    // result
+    pub fn contains(&self, p: &Point) -> bool {
+        let mut inside = false;
+        for segment in &self.segments {
+            let toggle = match segment {
+                Segment::Line(a, b) => ray_crosses(p, a, b),
+                Segment::Arc(arc) => {
+                    ray_crosses(p, &arc.start, &arc.end) != arc.segment_contains(p)
+                }
+                Segment::Circle(centre, radius) => p.distance(centre) < *radius,
+            };
+            inside ^= toggle;
+        }
+        inside
+    }
+
+    // Distance from point to the nearest part of the boundary
+    pub fn boundary_distance(&self, p: &Point) -> f64 {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Line(a, b) => point_line(p, a, b),
+                Segment::Arc(arc) => point_arc(p, arc),
+                Segment::Circle(centre, radius) => (p.distance(centre) - radius).abs(),
+            })
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    // Distance from point to the region, zero if inside
+    pub fn distance(&self, p: &Point) -> f64 {
+        if self.contains(p) {
+            0.0
+        } else {
+            self.boundary_distance(p)
+        }
+    }
+
+    // Distance from line segment ab to the region, zero if the line is
+    // inside or crosses the boundary
+    pub fn line_distance(&self, a: &Point, b: &Point) -> f64 {
+        if self.contains(a) || self.contains(b) {
+            return 0.0;
+        }
+
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Line(c, d) => line_line(a, b, c, d),
+                Segment::Arc(arc) => line_arc(a, b, arc),
+                Segment::Circle(centre, radius) => line_circle(a, b, centre, *radius),
+            })
+            .fold(f64::INFINITY, f64::min)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    fn pt(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+
+    // Build shape directly in the plane, with the projection origin at 0N 0E
+    // so one nautical mile is one minute of latitude/longitude
+    fn shape(boundary: &str) -> Shape {
+        let boundary: Vec<Boundary> = serde_json::from_str(boundary).unwrap();
+        Shape::new(&boundary, &Projection::new(LatLon { lat: 0.0, lon: 0.0 }))
+    }
+
+    // 10 nm square, from (0, 0) to (10, 10), with a semicircle of radius 5
+    // bulging out of the east side
+    fn square_arc() -> Shape {
+        shape(
+            r#"[{"line": ["000000N 0000000E", "001000N 0000000E", "001000N 0001000E"]},
+                {"arc": {"centre": "000500N 0001000E", "dir": "cw",
+                         "radius": "5 nm", "to": "000000N 0001000E"}}]"#,
+        )
+    }
+
+    #[test]
+    fn contains() {
+        let s = square_arc();
+        assert!(s.contains(&pt(5.0, 5.0)));
+        assert!(s.contains(&pt(14.0, 5.0)));
+        assert!(s.contains(&pt(12.0, 8.0)));
+        assert!(!s.contains(&pt(15.5, 5.0)));
+        assert!(!s.contains(&pt(13.0, 9.5)));
+        assert!(!s.contains(&pt(-1.0, 5.0)));
+        assert!(!s.contains(&pt(5.0, 11.0)));
+
+        // Same semicircle, but anti-clockwise, i.e. cut into the square
+        let s = shape(
+            r#"[{"line": ["000000N 0000000E", "001000N 0000000E", "001000N 0001000E"]},
+                {"arc": {"centre": "000500N 0001000E", "dir": "ccw",
+                         "radius": "5 nm", "to": "000000N 0001000E"}}]"#,
+        );
+        assert!(s.contains(&pt(2.0, 5.0)));
+        assert!(!s.contains(&pt(8.0, 5.0)));
+        assert!(s.contains(&pt(4.0, 0.5)));
+        assert!(!s.contains(&pt(12.0, 5.0)));
+
+        let s = shape(r#"[{"circle": {"centre": "000000N 0000000E", "radius": "3 nm"}}]"#);
+        assert!(s.contains(&pt(2.0, 2.0)));
+        assert!(!s.contains(&pt(2.5, 2.5)));
+    }
+
+    #[test]
+    fn distance() {
+        let s = square_arc();
+        assert_eq!(s.distance(&pt(5.0, 5.0)), 0.0);
+        assert!(close(s.boundary_distance(&pt(5.0, 5.0)), 5.0));
+        assert!(close(s.boundary_distance(&pt(14.0, 5.0)), 1.0));
+        assert!(close(s.distance(&pt(18.0, 5.0)), 3.0));
+        assert!(close(s.distance(&pt(-2.0, 5.0)), 2.0));
+
+        // Nearest point is on the arc, not its end point
+        assert!(close(s.distance(&pt(13.0, 14.0)), 90.0_f64.sqrt() - 5.0));
+    }
+
+    #[test]
+    fn line_distance() {
+        let s = square_arc();
+
+        // Crossing, inside, and parallel to the west side
+        assert_eq!(s.line_distance(&pt(-5.0, 5.0), &pt(5.0, 5.0)), 0.0);
+        assert_eq!(s.line_distance(&pt(1.0, 1.0), &pt(2.0, 2.0)), 0.0);
+        assert!(close(s.line_distance(&pt(-3.0, 0.0), &pt(-3.0, 10.0)), 3.0));
+
+        // North-south line east of the arc, and a line crossing the arc only
+        assert!(close(
+            s.line_distance(&pt(17.0, -5.0), &pt(17.0, 15.0)),
+            2.0
+        ));
+        assert_eq!(s.line_distance(&pt(14.0, 20.0), &pt(14.0, -20.0)), 0.0);
+        assert_eq!(s.line_distance(&pt(11.0, 11.0), &pt(16.0, 6.0)), 0.0);
+
+        // Line north of the arc, nearest to the top of the arc
+        assert!(close(
+            s.line_distance(&pt(10.0, 13.0), &pt(20.0, 13.0)),
+            3.0
+        ));
+
+        let s = shape(r#"[{"circle": {"centre": "000000N 0000000E", "radius": "3 nm"}}]"#);
+        assert!(close(s.line_distance(&pt(-10.0, 4.0), &pt(10.0, 4.0)), 1.0));
+        assert_eq!(s.line_distance(&pt(-10.0, 2.0), &pt(10.0, 2.0)), 0.0);
+    }
+}
@@ -0,0 +1,36 @@
+// Test data shared by the unit tests
+
+use crate::yaixm::Yaixm;
+
+pub const YAIXM: &str = r#"{
+          "airspace": [
+            {"name": "BRIZE NORTON", "type": "CTR", "class": "D", "id": "brize",
+             "geometry": [{"lower": "SFC", "upper": "3500 ft", "boundary": [
+               {"circle": {"centre": "514500N 0013500W", "radius": "5 nm"}}]}]}
+          ],
+          "rat": [
+            {"name": "ROYAL FLIGHT", "type": "OTHER", "localtype": "RAT", "id": "royal",
+             "geometry": [
+               {"lower": "SFC", "upper": "FL45", "boundary": [
+                 {"circle": {"centre": "520000N 0010000W", "radius": "2 nm"}}]},
+               {"lower": "FL70", "upper": "FL95", "boundary": [
+                 {"circle": {"centre": "520000N 0010000W", "radius": "10 nm"}}]}]},
+            {"name": "AIR SHOW", "type": "OTHER", "localtype": "RAT",
+             "geometry": [{"lower": "SFC", "upper": "FL45", "boundary": [
+               {"circle": {"centre": "530000N 0010000W", "radius": "2 nm"}}]}]}
+          ],
+          "loa": [],
+          "obstacle": [
+            {"name": "MAST", "elevation": "1200 ft", "position": "530000N 0020000W"}
+          ],
+          "service": [
+            {"callsign": "ROYAL INFO", "frequency": 123.45, "controls": ["royal"]},
+            {"callsign": "BRIZE ZONE", "frequency": 119.0, "controls": ["brize"]}
+          ],
+          "release": {"airac_date": "2022-09-08T00:00:00Z", "timestamp": "",
+                      "schema_version": 1, "note": "Test data", "commit": "abc"}
+        }"#;
+
+pub fn yaixm() -> Yaixm {
+    serde_json::from_str(YAIXM).unwrap()
+}
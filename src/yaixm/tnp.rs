@@ -0,0 +1,197 @@
+use crate::settings::Settings;
+use crate::yaixm::convert::{
+    airfilter, format_level, header, merged_airspace, volume_name, volume_type,
+};
+use crate::yaixm::geo::{Distance, LatLon};
+use crate::yaixm::{Arc, Boundary, Circle, Direction, Feature, IcaoClass, IcaoType, Volume, Yaixm};
+use chrono::{DateTime, Utc};
+
+// TNP lat/lon format, e.g. N512345 W0012345
+fn format_latlon(latlon: &LatLon) -> String {
+    let (lat, lon) = (latlon.lat_dms(), latlon.lon_dms());
+    format!(
+        "{}{:02}{:02}{:02} {}{:03}{:02}{:02}",
+        lat.hemisphere, lat.deg, lat.min, lat.sec, lon.hemisphere, lon.deg, lon.min, lon.sec
+    )
+}
+
+// TNP distance format (nautical miles)
+fn format_distance(distance: &Distance) -> String {
+    format!("{:.2}", distance.nm())
+}
+
+// Map OpenAir type to TNP type and class
+fn tnp_type(
+    feature: &Feature,
+    volume: &Volume,
+    settings: &Settings,
+) -> (&'static str, &'static str) {
+    let icao_class = volume
+        .icao_class
+        .or(feature.icao_class)
+        .unwrap_or(IcaoClass::G)
+        .as_str();
+
+    match volume_type(feature, volume, settings) {
+        class @ ("A" | "B" | "C" | "D" | "E" | "F" | "G") => {
+            let tnp_type = match feature.icao_type {
+                IcaoType::Awy => "AIRWAYS",
+                IcaoType::Atz | IcaoType::Cta | IcaoType::Ctr | IcaoType::Tma => "CTA/CTR",
+                _ => "OTHER",
+            };
+            (tnp_type, class)
+        }
+        "CTA" | "CTR" => ("CTA/CTR", icao_class),
+        "P" => ("PROHIBITED", icao_class),
+        "Q" => ("DANGER", icao_class),
+        "R" => ("RESTRICTED", icao_class),
+        "W" => ("GSEC", icao_class),
+        "MATZ" => ("MATZ", icao_class),
+        "TMZ" => ("TMZ", icao_class),
+        _ => ("OTHER", icao_class),
+    }
+}
+
+fn do_point(point: &LatLon) -> String {
+    format!("POINT={}\n", format_latlon(point))
+}
+
+fn do_circle(circle: &Circle) -> String {
+    format!(
+        "CIRCLE RADIUS={} CENTRE={}\n",
+        format_distance(&circle.radius),
+        format_latlon(&circle.centre)
+    )
+}
+
+fn do_arc(arc: &Arc) -> String {
+    let dir = if arc.dir == Direction::Cw {
+        "CLOCKWISE"
+    } else {
+        "ANTI-CLOCKWISE"
+    };
+
+    format!(
+        "{} RADIUS={} CENTRE={} TO={}\n",
+        dir,
+        format_distance(&arc.radius),
+        format_latlon(&arc.centre),
+        format_latlon(&arc.to)
+    )
+}
+
+fn do_boundary(boundary: &[Boundary]) -> String {
+    let mut out = String::new();
+    let mut prev = None;
+
+    for segment in boundary {
+        match segment {
+            Boundary::Line(line) => {
+                for point in line {
+                    out.push_str(&do_point(point));
+                }
+                prev = line.last();
+            }
+            Boundary::Arc(arc) => {
+                out.push_str(&do_arc(arc));
+                prev = Some(&arc.to);
+            }
+            Boundary::Circle(circle) => out.push_str(&do_circle(circle)),
+        }
+    }
+
+    // Close the polygon
+    if let Boundary::Line(line) = &boundary[0] {
+        if Some(&line[0]) != prev {
+            out.push_str(&do_point(&line[0]));
+        }
+    }
+
+    out
+}
+
+fn do_volume(feature: &Feature, volume: &Volume, n: usize, settings: &Settings) -> String {
+    let (tnp_type, tnp_class) = tnp_type(feature, volume, settings);
+
+    let mut out = format!("TYPE={}\nCLASS={}\n", tnp_type, tnp_class);
+    out.push_str(&format!(
+        "TITLE={}\n",
+        volume_name(feature, volume, n, settings)
+    ));
+    if let Some(freq) = volume.frequency {
+        out.push_str(&format!("RADIO={:.3}\n", freq));
+    }
+    out.push_str(&format!(
+        "TOPS={}\nBASE={}\n",
+        format_level(&volume.upper),
+        format_level(&volume.lower)
+    ));
+    out.push_str(&do_boundary(&volume.boundary));
+    out
+}
+
+// Generate Tim Newport-Peace (SUA) data
+pub fn tnp(yaixm: &Yaixm, settings: &Settings, produced: DateTime<Utc>) -> String {
+    let mut output = header("UK Airspace", &yaixm.release, produced, settings, "#");
+    output.push_str("#\nINCLUDE=YES\n");
+
+    for feature in merged_airspace(yaixm, settings) {
+        for (n, volume) in feature.geometry.iter().enumerate() {
+            if airfilter(&feature, volume, settings) {
+                output.push('\n');
+                output.push_str(&do_volume(&feature, volume, n, settings));
+            }
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Format;
+    use crate::yaixm::testdata::yaixm;
+
+    #[test]
+    fn tnp_volumes() {
+        let mut settings = Settings::default();
+        settings.options.format = Format::Tnp;
+        settings.options.radio = true;
+        settings.rat.insert("ROYAL FLIGHT".to_string());
+
+        let sua = tnp(&yaixm(), &settings, Utc::now());
+        assert!(sua.starts_with("# UK Airspace\n"));
+        assert!(sua.contains(
+            "\nTYPE=CTA/CTR\n\
+             CLASS=D\n\
+             TITLE=BRIZE NORTON 119.000\n\
+             RADIO=119.000\n\
+             TOPS=3500ALT\n\
+             BASE=SFC\n\
+             CIRCLE RADIUS=5.00 CENTRE=N514500 W0013500\n"
+        ));
+        assert!(sua.contains(
+            "\nTYPE=PROHIBITED\n\
+             CLASS=G\n\
+             TITLE=ROYAL FLIGHT 123.450\n"
+        ));
+    }
+
+    #[test]
+    fn tnp_boundary() {
+        let boundary: Vec<Boundary> = serde_json::from_str(
+            r#"[{"line": ["544800N 0020000W", "544800N 0014239W"]},
+                {"arc": {"centre": "544800N 0020000W", "dir": "ccw",
+                         "radius": "18.52 km", "to": "544800N 0021721W"}}]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            do_boundary(&boundary),
+            "POINT=N544800 W0020000\n\
+             POINT=N544800 W0014239\n\
+             ANTI-CLOCKWISE RADIUS=10.00 CENTRE=N544800 W0020000 TO=N544800 W0021721\n\
+             POINT=N544800 W0020000\n"
+        );
+    }
+}
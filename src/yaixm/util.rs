@@ -1,15 +1,4 @@
 use crate::yaixm::{IcaoType, LocalType, Yaixm};
-use gloo_net::http::Request;
-use gloo_net::Error;
-
-// Get YAIXM data from server
-pub async fn fetch_yaixm() -> Result<Yaixm, Error> {
-    let result = Request::get("yaixm.json").send().await;
-    match result {
-        Ok(response) => response.json().await,
-        Err(e) => Err(e),
-    }
-}
 
 // List of RAT names
 pub fn rat_names(yaixm: &Yaixm) -> Vec<String> {